@@ -0,0 +1,72 @@
+pub mod csv;
+pub mod error;
+pub mod idx;
+
+pub use self::csv::{Column, CsvOptions, MissingValues};
+pub use self::error::DatasetError;
+pub use self::idx::IdxArray;
+
+use crate::trainer::TrainingData;
+
+/// A list of training samples with the names of their input and output values
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dataset {
+    samples : Vec<TrainingData>,
+    input_names : Vec<String>,
+    output_names : Vec<String>
+}
+
+impl Dataset {
+    /// Create a dataset from samples. Values are named by their index.
+    pub fn new(samples : Vec<TrainingData>) -> Dataset {
+        let input_count = samples.first().map_or(0, |s| s.input().len());
+        let output_count = samples.first().map_or(0, |s| s.output().len());
+        return Dataset {
+            samples,
+            input_names: (0..input_count).map(|i| i.to_string()).collect(),
+            output_names: (0..output_count).map(|i| i.to_string()).collect()
+        };
+    }
+
+    /// Create a dataset from samples and the names of their input and output values
+    pub fn with_names(samples : Vec<TrainingData>, input_names : Vec<String>, output_names : Vec<String>) -> Dataset {
+        return Dataset { samples, input_names, output_names };
+    }
+
+    /// All samples, ready to be passed to a trainer
+    pub fn samples(&self) -> &[TrainingData] {
+        return &self.samples;
+    }
+
+    pub fn into_samples(self) -> Vec<TrainingData> {
+        return self.samples;
+    }
+
+    /// Names of the input values, one per input of a sample. One-hot encoded columns are named "column=category".
+    pub fn input_names(&self) -> &[String] {
+        return &self.input_names;
+    }
+
+    /// Names of the output values, one per output of a sample
+    pub fn output_names(&self) -> &[String] {
+        return &self.output_names;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.samples.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.samples.is_empty();
+    }
+
+    /// Number of input values of every sample
+    pub fn input_size(&self) -> usize {
+        return self.input_names.len();
+    }
+
+    /// Number of output values of every sample
+    pub fn output_size(&self) -> usize {
+        return self.output_names.len();
+    }
+}
@@ -0,0 +1,376 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use crate::trainer::TrainingData;
+
+use super::{Dataset, DatasetError};
+
+/// A column of a csv file, by its index starting at 0 or by its name in the header
+#[derive(Clone, Debug, PartialEq)]
+pub enum Column {
+    Index(usize),
+    Name(String)
+}
+
+impl From<usize> for Column {
+    fn from(index : usize) -> Column {
+        return Column::Index(index);
+    }
+}
+
+impl From<&str> for Column {
+    fn from(name : &str) -> Column {
+        return Column::Name(name.to_string());
+    }
+}
+
+/// What to do with empty fields and fields that match one of the missing markers
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MissingValues {
+    /// Fail with DatasetError::MissingValue
+    Error,
+    /// Leave out every line with a missing value in a used column
+    SkipRow,
+    /// Replace missing numbers with the given value
+    Fill(f64),
+    /// Replace missing numbers with the mean of their column
+    Mean
+}
+
+/// Settings for reading csv and tsv files
+#[derive(Clone, Debug, PartialEq)]
+pub struct CsvOptions {
+    /// Character between the fields of a line
+    pub delimiter : char,
+    /// Number of lines skipped at the start of the file, before the header
+    pub skip_lines : usize,
+    /// Whether the first line after the skipped ones names the columns
+    pub has_header : bool,
+    /// Columns used as inputs. If empty, every column that is not a target is an input.
+    pub input_columns : Vec<Column>,
+    /// Columns used as expected outputs
+    pub target_columns : Vec<Column>,
+    /// Columns that hold categories instead of numbers. Each is one-hot encoded into one value per category, sorted by name.
+    pub categorical_columns : Vec<Column>,
+    /// Missing categories are always encoded as all zeros, unless the policy is Error or SkipRow
+    pub missing_values : MissingValues,
+    /// Field values that count as missing, besides empty fields
+    pub missing_markers : Vec<String>
+}
+
+impl Default for CsvOptions {
+    fn default() -> CsvOptions {
+        return CsvOptions {
+            delimiter: ',',
+            skip_lines: 0,
+            has_header: true,
+            input_columns: vec![],
+            target_columns: vec![],
+            categorical_columns: vec![],
+            missing_values: MissingValues::Error,
+            missing_markers: vec!["NA".to_string(), "NaN".to_string(), "?".to_string()]
+        };
+    }
+}
+
+impl CsvOptions {
+    /// Default options for tab separated files
+    pub fn tsv() -> CsvOptions {
+        return CsvOptions { delimiter: '\t', ..CsvOptions::default() };
+    }
+
+    fn is_missing(&self, field : &str) -> bool {
+        return field.is_empty() || self.missing_markers.iter().any(|m| m == field);
+    }
+}
+
+/// How the values of one column are turned into numbers
+enum Encoding {
+    /// Parsed value of every row, and the value used for missing ones
+    Numeric(Vec<Option<f64>>, f64),
+    /// Sorted categories
+    Categorical(Vec<String>)
+}
+
+impl Dataset {
+    /// Load a csv or tsv file
+    pub fn from_csv<P: AsRef<Path>>(path : P, options : &CsvOptions) -> Result<Dataset, DatasetError> {
+        return Dataset::parse_csv(&fs::read_to_string(path)?, options);
+    }
+
+    /// Read a dataset from the text of a csv or tsv file
+    pub fn parse_csv(text : &str, options : &CsvOptions) -> Result<Dataset, DatasetError> {
+        let mut lines = text.lines().enumerate().skip(options.skip_lines).filter(|(_, line)| !line.trim().is_empty());
+
+        let header = if options.has_header {
+            match lines.next() {
+                Some((_, line)) => Some(split_fields(line, options.delimiter)),
+                None => return Err(DatasetError::Empty)
+            }
+        }
+        else {
+            None
+        };
+        // Rows with the line number they were read from
+        let mut rows : Vec<(usize, Vec<String>)> = lines.map(|(i, line)| (i + 1, split_fields(line, options.delimiter))).collect();
+
+        let columns = match (&header, rows.first()) {
+            (Some(names), _) => names.len(),
+            (None, Some((_, fields))) => fields.len(),
+            (None, None) => return Err(DatasetError::Empty)
+        };
+        for (line, fields) in &rows {
+            if fields.len() != columns {
+                return Err(DatasetError::RowLength { line: *line, expected: columns, actual: fields.len() });
+            }
+        }
+        let names = header.unwrap_or_else(|| (0..columns).map(|c| c.to_string()).collect());
+
+        let targets = resolve_columns(&options.target_columns, &names)?;
+        let inputs = if options.input_columns.is_empty() {
+            (0..columns).filter(|c| !targets.contains(c)).collect()
+        }
+        else {
+            resolve_columns(&options.input_columns, &names)?
+        };
+        let categorical = resolve_columns(&options.categorical_columns, &names)?;
+        let used : Vec<usize> = inputs.iter().chain(targets.iter()).copied().collect();
+
+        if options.missing_values == MissingValues::SkipRow {
+            rows.retain(|(_, fields)| !used.iter().any(|c| options.is_missing(&fields[*c])));
+        }
+        if rows.is_empty() {
+            return Err(DatasetError::Empty);
+        }
+
+        // Work out how every used column is encoded
+        let mut encodings : Vec<Option<Encoding>> = (0..columns).map(|_| None).collect();
+        for c in used.iter().copied() {
+            if encodings[c].is_some() {
+                continue;
+            }
+            if options.missing_values == MissingValues::Error {
+                for (line, fields) in &rows {
+                    if options.is_missing(&fields[c]) {
+                        return Err(DatasetError::MissingValue { line: *line, column: names[c].clone() });
+                    }
+                }
+            }
+
+            if categorical.contains(&c) {
+                let categories : BTreeSet<&String> = rows.iter().map(|(_, fields)| &fields[c]).filter(|f| !options.is_missing(f)).collect();
+                encodings[c] = Some(Encoding::Categorical(categories.into_iter().cloned().collect()));
+                continue;
+            }
+
+            let mut values = vec![];
+            for (line, fields) in &rows {
+                if options.is_missing(&fields[c]) {
+                    values.push(None);
+                    continue;
+                }
+                match fields[c].parse::<f64>() {
+                    Ok(value) if value.is_finite() => values.push(Some(value)),
+                    _ => return Err(DatasetError::Parse { line: *line, column: names[c].clone(), value: fields[c].clone() })
+                }
+            }
+            let fill = match options.missing_values {
+                MissingValues::Fill(value) => value,
+                MissingValues::Mean if values.iter().any(|v| v.is_none()) => {
+                    let present : Vec<f64> = values.iter().flatten().copied().collect();
+                    if present.is_empty() {
+                        return Err(DatasetError::NoValues(names[c].clone()));
+                    }
+                    present.iter().sum::<f64>() / present.len() as f64
+                },
+                _ => 0.0
+            };
+            encodings[c] = Some(Encoding::Numeric(values, fill));
+        }
+
+        let mut samples = vec![];
+        for r in 0..rows.len() {
+            let input = encode_row(&inputs, &encodings, &rows[r].1, r);
+            let output = encode_row(&targets, &encodings, &rows[r].1, r);
+            samples.push(TrainingData::new(input, output));
+        }
+
+        return Ok(Dataset::with_names(samples, encoded_names(&inputs, &encodings, &names), encoded_names(&targets, &encodings, &names)));
+    }
+}
+
+/// Split a line at the delimiter. Fields can be quoted with double quotes to contain the delimiter, "" is a quote inside a quoted field.
+fn split_fields(line : &str, delimiter : char) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            if c != '"' {
+                field.push(c);
+            }
+            else if chars.peek() == Some(&'"') {
+                field.push('"');
+                chars.next();
+            }
+            else {
+                quoted = false;
+            }
+        }
+        else if c == '"' {
+            quoted = true;
+        }
+        else if c == delimiter {
+            fields.push(field.trim().to_string());
+            field.clear();
+        }
+        else {
+            field.push(c);
+        }
+    }
+    fields.push(field.trim().to_string());
+    return fields;
+}
+
+/// Indices of the given columns
+fn resolve_columns(columns : &[Column], names : &[String]) -> Result<Vec<usize>, DatasetError> {
+    let mut indices = vec![];
+    for column in columns {
+        match column {
+            Column::Index(index) => {
+                if *index >= names.len() {
+                    return Err(DatasetError::ColumnOutOfRange { column: *index, columns: names.len() });
+                }
+                indices.push(*index);
+            },
+            Column::Name(name) => match names.iter().position(|n| n == name) {
+                Some(index) => indices.push(index),
+                None => return Err(DatasetError::UnknownColumn(name.clone()))
+            }
+        }
+    }
+    return Ok(indices);
+}
+
+/// Values of the given columns of one row, with categorical columns one-hot encoded
+fn encode_row(columns : &[usize], encodings : &[Option<Encoding>], fields : &[String], row : usize) -> Vec<f64> {
+    let mut values = vec![];
+    for c in columns {
+        match &encodings[*c] {
+            Some(Encoding::Numeric(parsed, fill)) => values.push(parsed[row].unwrap_or(*fill)),
+            Some(Encoding::Categorical(categories)) => values.extend(categories.iter().map(|category| if *category == fields[*c] { 1.0 } else { 0.0 })),
+            None => ()
+        }
+    }
+    return values;
+}
+
+/// Names of the values encode_row creates for the given columns
+fn encoded_names(columns : &[usize], encodings : &[Option<Encoding>], names : &[String]) -> Vec<String> {
+    let mut encoded = vec![];
+    for c in columns {
+        match &encodings[*c] {
+            Some(Encoding::Categorical(categories)) => encoded.extend(categories.iter().map(|category| format!("{}={}", names[*c], category))),
+            _ => encoded.push(names[*c].clone())
+        }
+    }
+    return encoded;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT : &str = "size,color,price\n1.5,red,10\n2.5,blue,NA\n,red,30\n3.5,green,40\n";
+
+    fn options(missing_values : MissingValues) -> CsvOptions {
+        return CsvOptions {
+            target_columns: vec!["price".into()],
+            categorical_columns: vec!["color".into()],
+            missing_values,
+            ..CsvOptions::default()
+        };
+    }
+
+    fn inputs(dataset : &Dataset) -> Vec<Vec<f64>> {
+        return dataset.samples().iter().map(|s| s.input().to_vec()).collect();
+    }
+
+    fn outputs(dataset : &Dataset) -> Vec<f64> {
+        return dataset.samples().iter().map(|s| s.output()[0]).collect();
+    }
+
+    #[test]
+    fn split_fields_handles_quotes() {
+        assert_eq!(split_fields("a, b ,c", ','), vec!["a", "b", "c"]);
+        assert_eq!(split_fields("\"x,y\",2", ','), vec!["x,y", "2"]);
+        assert_eq!(split_fields("\"say \"\"hi\"\"\";3", ';'), vec!["say \"hi\"", "3"]);
+        assert_eq!(split_fields("1,,", ','), vec!["1", "", ""]);
+        assert_eq!(split_fields("a\tb", '\t'), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn categorical_columns_are_one_hot_encoded() {
+        let dataset = Dataset::parse_csv(TEXT, &options(MissingValues::Fill(-1.0))).unwrap();
+        // Categories are sorted by name: blue, green, red
+        assert_eq!(dataset.input_names(), &["size", "color=blue", "color=green", "color=red"]);
+        assert_eq!(dataset.output_names(), &["price"]);
+        assert_eq!(inputs(&dataset)[0], vec![1.5, 0.0, 0.0, 1.0]);
+        assert_eq!(inputs(&dataset)[1], vec![2.5, 1.0, 0.0, 0.0]);
+        assert_eq!(inputs(&dataset)[3], vec![3.5, 0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn missing_values_follow_the_policy() {
+        // Input columns are checked before targets, so the empty size is found before the missing price
+        assert!(matches!(Dataset::parse_csv(TEXT, &options(MissingValues::Error)), Err(DatasetError::MissingValue { line: 4, column }) if column == "size"));
+
+        let skipped = Dataset::parse_csv(TEXT, &options(MissingValues::SkipRow)).unwrap();
+        assert_eq!(outputs(&skipped), vec![10.0, 40.0]);
+
+        let filled = Dataset::parse_csv(TEXT, &options(MissingValues::Fill(-1.0))).unwrap();
+        assert_eq!(outputs(&filled), vec![10.0, -1.0, 30.0, 40.0]);
+        assert_eq!(inputs(&filled)[2][0], -1.0);
+
+        let mean = Dataset::parse_csv(TEXT, &options(MissingValues::Mean)).unwrap();
+        assert_eq!(outputs(&mean), vec![10.0, 80.0 / 3.0, 30.0, 40.0]);
+        assert_eq!(inputs(&mean)[2][0], 2.5);
+
+        // A missing category is all zeros
+        let text = "color,price\nred,1\n?,2\n";
+        let dataset = Dataset::parse_csv(text, &CsvOptions { missing_values: MissingValues::Fill(0.0), ..options(MissingValues::Error) }).unwrap();
+        assert_eq!(inputs(&dataset), vec![vec![1.0], vec![0.0]]);
+
+        let text = "a,b\nNA,1\n?,2\n";
+        let options = CsvOptions { target_columns: vec!["b".into()], missing_values: MissingValues::Mean, ..CsvOptions::default() };
+        assert!(matches!(Dataset::parse_csv(text, &options), Err(DatasetError::NoValues(column)) if column == "a"));
+    }
+
+    #[test]
+    fn columns_by_index_without_header() {
+        let options = CsvOptions {
+            has_header: false,
+            skip_lines: 1,
+            input_columns: vec![2.into(), 0.into()],
+            target_columns: vec![1.into()],
+            ..CsvOptions::tsv()
+        };
+        let dataset = Dataset::parse_csv("# comment\n1\t2\t3\n4\t5\t6\n", &options).unwrap();
+        assert_eq!(inputs(&dataset), vec![vec![3.0, 1.0], vec![6.0, 4.0]]);
+        assert_eq!(outputs(&dataset), vec![2.0, 5.0]);
+        assert_eq!(dataset.input_names(), &["2", "0"]);
+    }
+
+    #[test]
+    fn malformed_files_are_rejected() {
+        let options = options(MissingValues::Fill(0.0));
+        assert!(matches!(Dataset::parse_csv("size,color,price\n1,red,2\n3,blue\n", &options), Err(DatasetError::RowLength { line: 3, expected: 3, actual: 2 })));
+        assert!(matches!(Dataset::parse_csv("size,colour,price\n1,red,2\n", &options), Err(DatasetError::UnknownColumn(column)) if column == "color"));
+        assert!(matches!(Dataset::parse_csv("size,color,price\n1,red,2\nbig,red,3\n", &options), Err(DatasetError::Parse { line: 3, column, value }) if column == "size" && value == "big"));
+        assert!(matches!(Dataset::parse_csv("size,color,price\n1,red,inf\n", &options), Err(DatasetError::Parse { line: 2, .. })));
+        assert!(matches!(Dataset::parse_csv("a,b\n1,2\n", &CsvOptions { target_columns: vec![5.into()], ..CsvOptions::default() }), Err(DatasetError::ColumnOutOfRange { column: 5, columns: 2 })));
+        assert!(matches!(Dataset::parse_csv("size,color,price\n", &options), Err(DatasetError::Empty)));
+    }
+}
@@ -0,0 +1,55 @@
+use std::fmt;
+use std::io;
+
+/// Error when loading a dataset. Lines are counted from 1, like in a text editor.
+#[derive(Debug)]
+pub enum DatasetError {
+    Io(io::Error),
+    /// A column given by name does not exist in the header
+    UnknownColumn(String),
+    /// A column index is outside of the columns of the file
+    ColumnOutOfRange { column : usize, columns : usize },
+    /// A line has a different number of fields than the first line
+    RowLength { line : usize, expected : usize, actual : usize },
+    /// A numeric column contains a value that is not a number
+    Parse { line : usize, column : String, value : String },
+    /// A value is missing and the policy does not allow that
+    MissingValue { line : usize, column : String },
+    /// A numeric column has no values at all, so its mean can not replace missing values
+    NoValues(String),
+    /// The data ended early or contains invalid values
+    Corrupt(String),
+    /// The file does not contain any samples
+    Empty
+}
+
+impl fmt::Display for DatasetError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DatasetError::Io(e) => write!(f, "Could not access dataset file: {}", e),
+            DatasetError::UnknownColumn(name) => write!(f, "Unknown column {}", name),
+            DatasetError::ColumnOutOfRange { column, columns } => write!(f, "Column {} does not exist, the data has {} columns", column, columns),
+            DatasetError::RowLength { line, expected, actual } => write!(f, "Line {} has {} fields, expected {}", line, actual, expected),
+            DatasetError::Parse { line, column, value } => write!(f, "Line {}: value {:?} of column {} is not a number", line, value, column),
+            DatasetError::MissingValue { line, column } => write!(f, "Line {}: missing value in column {}", line, column),
+            DatasetError::NoValues(column) => write!(f, "Column {} has no values", column),
+            DatasetError::Corrupt(msg) => write!(f, "Corrupt dataset: {}", msg),
+            DatasetError::Empty => write!(f, "Dataset contains no samples")
+        }
+    }
+}
+
+impl std::error::Error for DatasetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DatasetError::Io(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for DatasetError {
+    fn from(e : io::Error) -> DatasetError {
+        return DatasetError::Io(e);
+    }
+}
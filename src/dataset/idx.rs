@@ -0,0 +1,185 @@
+use std::fs;
+use std::path::Path;
+
+use crate::trainer::TrainingData;
+
+use super::{Dataset, DatasetError};
+
+/// Type code of unsigned bytes, the only element type used by MNIST and Fashion-MNIST
+const IDX_UNSIGNED_BYTE : u8 = 0x08;
+
+/// Contents of an IDX file: the size of every dimension and the elements in row-major order
+#[derive(Clone, Debug, PartialEq)]
+pub struct IdxArray {
+    pub dimensions : Vec<usize>,
+    pub data : Vec<u8>
+}
+
+impl IdxArray {
+    /// Read an uncompressed IDX file
+    pub fn load<P: AsRef<Path>>(path : P) -> Result<IdxArray, DatasetError> {
+        return IdxArray::parse(&fs::read(path)?);
+    }
+
+    /// Read an IDX array of unsigned bytes from the bytes of a file
+    pub fn parse(bytes : &[u8]) -> Result<IdxArray, DatasetError> {
+        if bytes.len() < 4 || bytes[0] != 0 || bytes[1] != 0 {
+            return Err(DatasetError::Corrupt("missing IDX magic number".to_string()));
+        }
+        if bytes[2] != IDX_UNSIGNED_BYTE {
+            return Err(DatasetError::Corrupt(format!("unsupported IDX element type {:#04x}", bytes[2])));
+        }
+
+        let dimension_count = bytes[3] as usize;
+        let header_size = 4 + 4 * dimension_count;
+        if bytes.len() < header_size {
+            return Err(DatasetError::Corrupt("IDX header ends early".to_string()));
+        }
+        let mut dimensions = vec![];
+        for d in 0..dimension_count {
+            let start = 4 + 4 * d;
+            dimensions.push(u32::from_be_bytes([bytes[start], bytes[start+1], bytes[start+2], bytes[start+3]]) as usize);
+        }
+
+        let size = dimensions.iter().try_fold(1usize, |size, d| size.checked_mul(*d));
+        if size != Some(bytes.len() - header_size) {
+            return Err(DatasetError::Corrupt(format!("IDX data has {} bytes, which does not fit the dimensions {:?}", bytes.len() - header_size, dimensions)));
+        }
+        return Ok(IdxArray { dimensions, data: bytes[header_size..].to_vec() });
+    }
+
+    /// Number of entries along the first dimension, e.g. images or labels
+    pub fn items(&self) -> usize {
+        return self.dimensions.first().copied().unwrap_or(0);
+    }
+}
+
+impl Dataset {
+    /// Load an image and a label file in IDX format, like the MNIST and Fashion-MNIST files.
+    /// The files must be uncompressed. See from_idx_arrays for the encoding.
+    pub fn from_idx<P: AsRef<Path>, Q: AsRef<Path>>(images_path : P, labels_path : Q, classes : usize) -> Result<Dataset, DatasetError> {
+        return Dataset::from_idx_arrays(&IdxArray::load(images_path)?, &IdxArray::load(labels_path)?, classes);
+    }
+
+    /// Create a sample for every image. The pixels are scaled from 0..255 to 0..1
+    /// and the label becomes a one-hot output with a value for each of the given number of classes.
+    pub fn from_idx_arrays(images : &IdxArray, labels : &IdxArray, classes : usize) -> Result<Dataset, DatasetError> {
+        if images.dimensions.len() < 2 {
+            return Err(DatasetError::Corrupt(format!("IDX images need at least two dimensions, got {:?}", images.dimensions)));
+        }
+        if labels.dimensions.len() != 1 {
+            return Err(DatasetError::Corrupt(format!("IDX labels need one dimension, got {:?}", labels.dimensions)));
+        }
+        if images.items() != labels.items() {
+            return Err(DatasetError::Corrupt(format!("{} images but {} labels", images.items(), labels.items())));
+        }
+        if images.items() == 0 {
+            return Err(DatasetError::Empty);
+        }
+
+        let pixels = images.data.len() / images.items();
+        let mut samples = vec![];
+        for i in 0..images.items() {
+            let label = labels.data[i] as usize;
+            if label >= classes {
+                return Err(DatasetError::Corrupt(format!("label {} of image {} is not one of the {} classes", label, i, classes)));
+            }
+            let input = images.data[i*pixels..(i+1)*pixels].iter().map(|p| *p as f64 / 255.0).collect();
+            let mut output = vec![0.0; classes];
+            output[label] = 1.0;
+            samples.push(TrainingData::new(input, output));
+        }
+
+        let input_names = (0..pixels).map(|p| format!("pixel{}", p)).collect();
+        let output_names = (0..classes).map(|c| c.to_string()).collect();
+        return Ok(Dataset::with_names(samples, input_names, output_names));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Header of an IDX file of unsigned bytes with the given dimensions
+    fn header(dimensions : &[u32]) -> Vec<u8> {
+        let mut bytes = vec![0, 0, IDX_UNSIGNED_BYTE, dimensions.len() as u8];
+        for d in dimensions {
+            bytes.extend_from_slice(&d.to_be_bytes());
+        }
+        return bytes;
+    }
+
+    /// Three images of 2x2 pixels and their labels
+    fn image_and_label_files() -> (Vec<u8>, Vec<u8>) {
+        let mut images = header(&[3, 2, 2]);
+        images.extend_from_slice(&[0, 255, 51, 102, 255, 255, 0, 0, 0, 0, 0, 0]);
+        let mut labels = header(&[3]);
+        labels.extend_from_slice(&[2, 0, 1]);
+        return (images, labels);
+    }
+
+    #[test]
+    fn parse_reads_dimensions_and_data() {
+        let (images, labels) = image_and_label_files();
+        let images = IdxArray::parse(&images).unwrap();
+        assert_eq!(images.dimensions, vec![3, 2, 2]);
+        assert_eq!(images.items(), 3);
+        assert_eq!(images.data.len(), 12);
+        assert_eq!(IdxArray::parse(&labels).unwrap(), IdxArray { dimensions: vec![3], data: vec![2, 0, 1] });
+    }
+
+    #[test]
+    fn images_are_scaled_and_labels_one_hot() {
+        let (images, labels) = image_and_label_files();
+        let dataset = Dataset::from_idx_arrays(&IdxArray::parse(&images).unwrap(), &IdxArray::parse(&labels).unwrap(), 3).unwrap();
+
+        assert_eq!(dataset.len(), 3);
+        assert_eq!(dataset.samples()[0].input(), &[0.0, 1.0, 0.2, 0.4]);
+        assert_eq!(dataset.samples()[0].output(), &[0.0, 0.0, 1.0]);
+        assert_eq!(dataset.samples()[1].output(), &[1.0, 0.0, 0.0]);
+        assert_eq!(dataset.samples()[2].output(), &[0.0, 1.0, 0.0]);
+        assert_eq!(dataset.input_names(), &["pixel0", "pixel1", "pixel2", "pixel3"]);
+        assert_eq!(dataset.output_names(), &["0", "1", "2"]);
+    }
+
+    #[test]
+    fn corrupt_files_are_rejected() {
+        let (images, _) = image_and_label_files();
+
+        let mut bad_magic = images.clone();
+        bad_magic[0] = 1;
+        assert!(matches!(IdxArray::parse(&bad_magic), Err(DatasetError::Corrupt(_))));
+
+        let mut bad_type = images.clone();
+        bad_type[2] = 0x0D;
+        assert!(matches!(IdxArray::parse(&bad_type), Err(DatasetError::Corrupt(_))));
+
+        assert!(matches!(IdxArray::parse(&images[..10]), Err(DatasetError::Corrupt(_))));
+        assert!(matches!(IdxArray::parse(&images[..images.len()-1]), Err(DatasetError::Corrupt(_))));
+        let mut extra_byte = images.clone();
+        extra_byte.push(0);
+        assert!(matches!(IdxArray::parse(&extra_byte), Err(DatasetError::Corrupt(_))));
+
+        // Dimensions whose product does not fit into usize
+        let mut huge = header(&[u32::MAX, u32::MAX, u32::MAX]);
+        huge.push(0);
+        assert!(matches!(IdxArray::parse(&huge), Err(DatasetError::Corrupt(_))));
+    }
+
+    #[test]
+    fn images_and_labels_must_match() {
+        let (images, labels) = image_and_label_files();
+        let images = IdxArray::parse(&images).unwrap();
+
+        let mut two_labels = header(&[2]);
+        two_labels.extend_from_slice(&[0, 1]);
+        assert!(matches!(Dataset::from_idx_arrays(&images, &IdxArray::parse(&two_labels).unwrap(), 3), Err(DatasetError::Corrupt(_))));
+
+        // Label 2 is not one of two classes
+        assert!(matches!(Dataset::from_idx_arrays(&images, &IdxArray::parse(&labels).unwrap(), 2), Err(DatasetError::Corrupt(_))));
+
+        let no_images = IdxArray::parse(&header(&[0, 2, 2])).unwrap();
+        let no_labels = IdxArray::parse(&header(&[0])).unwrap();
+        assert!(matches!(Dataset::from_idx_arrays(&no_images, &no_labels, 3), Err(DatasetError::Empty)));
+    }
+}
@@ -0,0 +1,49 @@
+pub mod classification;
+pub mod regression;
+
+pub use self::classification::{ClassMetrics, ClassificationReport};
+pub use self::regression::{Histogram, OutputMetrics, RegressionReport};
+
+use crate::network::{BatchWorkspace, Matrix, NetworkError, NeuralNetwork};
+use crate::trainer::{Trainer, TrainingData};
+
+/// Values of the output layer, one vector per sample
+type Outputs = Vec<Vec<f64>>;
+
+/// Preprocess the test set with the pipelines of the network and calculate the values of the output layer for every sample.
+/// Returns the outputs and the expected outputs, both as values of the output layer.
+fn layer_outputs(network : &NeuralNetwork, test_set : &[TrainingData]) -> Result<(Outputs, Outputs), NetworkError> {
+    let test_set = network.preprocess_training_set(test_set)?;
+    Trainer::check_training_set(&test_set, network)?;
+    if test_set.is_empty() {
+        return Ok((vec![], vec![]));
+    }
+    let inputs = Matrix::from_rows(test_set.iter().map(|ts| ts.input().to_vec()).collect());
+    let outputs = network.forward_batch(&inputs, &mut BatchWorkspace::new())?.to_rows();
+    return Ok((outputs, test_set.iter().map(|ts| ts.output().to_vec()).collect()));
+}
+
+/// Index of the largest value, the first one if there are several
+fn index_of_max(values : &[f64]) -> usize {
+    let mut max = 0;
+    for i in 1..values.len() {
+        if values[i] > values[max] {
+            max = i;
+        }
+    }
+    return max;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inputs_of_the_wrong_size_are_an_error() {
+        let network = NeuralNetwork::new(vec![2, 2]).unwrap();
+        let test_set = vec![TrainingData::new(vec![0.0, 1.0], vec![1.0, 0.0]), TrainingData::new(vec![1.0], vec![0.0, 1.0])];
+        assert_eq!(layer_outputs(&network, &test_set), Err(NetworkError::InputSizeMismatch { expected: 2, actual: 1 }));
+        assert!(ClassificationReport::evaluate(&network, &test_set).is_err());
+        assert!(RegressionReport::evaluate(&network, &test_set).is_err());
+    }
+}
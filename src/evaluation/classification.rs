@@ -0,0 +1,295 @@
+use std::fmt;
+
+use crate::network::{NetworkError, NeuralNetwork};
+use crate::trainer::TrainingData;
+
+/// Smallest probability used for the log-loss, so confident wrong outputs give a large but finite loss
+const PROBABILITY_EPSILON : f64 = 1e-15;
+
+/// Precision, recall and F1 score of one class, or their average over all classes
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ClassMetrics {
+    /// Fraction of the samples predicted as the class that belong to it
+    pub precision : f64,
+    /// Fraction of the samples of the class that were predicted as it
+    pub recall : f64,
+    /// Harmonic mean of precision and recall
+    pub f1 : f64,
+    /// Number of samples of the class
+    pub support : usize
+}
+
+impl ClassMetrics {
+    /// Metrics from the number of true positives, false positives and false negatives. Undefined fractions are 0.
+    fn from_counts(true_positives : usize, false_positives : usize, false_negatives : usize) -> ClassMetrics {
+        let precision = fraction(true_positives, true_positives + false_positives);
+        let recall = fraction(true_positives, true_positives + false_negatives);
+        let f1 = if precision + recall > 0.0 { 2.0 * precision * recall / (precision + recall) } else { 0.0 };
+        return ClassMetrics { precision, recall, f1, support: true_positives + false_negatives };
+    }
+}
+
+/// Quality of a classifier on a test set.
+/// A network with one output is a binary classifier, an output of 0.5 or more predicts class 1.
+/// A network with several outputs predicts the class of its largest output, the expected class is the largest expected output.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClassificationReport {
+    /// Names of the classes in the table, the class index by default
+    pub class_names : Vec<String>,
+    /// Number of samples of every class, indexed [actual class][predicted class]
+    pub confusion_matrix : Vec<Vec<usize>>,
+    /// Fraction of samples classified correctly
+    pub accuracy : f64,
+    /// Metrics of every class
+    pub classes : Vec<ClassMetrics>,
+    /// Unweighted mean of the metrics of all classes
+    pub macro_average : ClassMetrics,
+    /// Metrics of the summed counts of all classes
+    pub micro_average : ClassMetrics,
+    /// Area under the ROC curve, for classifiers with one output or two outputs. None if the test set does not contain both classes.
+    pub roc_auc : Option<f64>,
+    /// Mean cross-entropy between the outputs and expected outputs. Expects outputs that are probabilities, like those of sigmoid or softmax layers.
+    pub log_loss : f64
+}
+
+impl ClassificationReport {
+    /// Run the network over a test set with raw values and measure how well it classifies them.
+    /// The samples are preprocessed by the pipelines of the network, so the classes are the values of the output layer.
+    pub fn evaluate(network : &NeuralNetwork, test_set : &[TrainingData]) -> Result<ClassificationReport, NetworkError> {
+        let (outputs, expected) = super::layer_outputs(network, test_set)?;
+        return Ok(ClassificationReport::from_outputs(&outputs, &expected));
+    }
+
+    /// Create a report from values of the output layer and the expected values, one pair of vectors of the same size per sample
+    pub fn from_outputs(outputs : &[Vec<f64>], expected : &[Vec<f64>]) -> ClassificationReport {
+        let width = outputs.first().or(expected.first()).map_or(0, |o| o.len());
+        let class_count = if width == 1 { 2 } else { width };
+        let samples = outputs.len().min(expected.len());
+
+        let mut confusion_matrix = vec![vec![0; class_count]; class_count];
+        let mut log_loss = 0.0;
+        for s in 0..samples {
+            confusion_matrix[class_of(&expected[s])][class_of(&outputs[s])] += 1;
+            log_loss += cross_entropy(&outputs[s], &expected[s]);
+        }
+
+        let mut classes = vec![];
+        for c in 0..class_count {
+            let true_positives = confusion_matrix[c][c];
+            let false_positives = (0..class_count).map(|a| confusion_matrix[a][c]).sum::<usize>() - true_positives;
+            let false_negatives = confusion_matrix[c].iter().sum::<usize>() - true_positives;
+            classes.push(ClassMetrics::from_counts(true_positives, false_positives, false_negatives));
+        }
+
+        // Every wrong prediction is a false positive of one class and a false negative of another
+        let correct = (0..class_count).map(|c| confusion_matrix[c][c]).sum::<usize>();
+        let micro_average = ClassMetrics::from_counts(correct, samples - correct, samples - correct);
+        let macro_average = ClassMetrics {
+            precision: mean(classes.iter().map(|c| c.precision)),
+            recall: mean(classes.iter().map(|c| c.recall)),
+            f1: mean(classes.iter().map(|c| c.f1)),
+            support: samples
+        };
+
+        let roc_auc = if class_count == 2 {
+            let scores : Vec<(f64, bool)> = (0..samples).map(|s| (outputs[s][width-1], class_of(&expected[s]) == 1)).collect();
+            roc_auc(&scores)
+        }
+        else {
+            None
+        };
+
+        return ClassificationReport {
+            class_names: (0..class_count).map(|c| c.to_string()).collect(),
+            confusion_matrix,
+            accuracy: fraction(correct, samples),
+            classes,
+            macro_average,
+            micro_average,
+            roc_auc,
+            log_loss: if samples > 0 { log_loss / samples as f64 } else { 0.0 }
+        };
+    }
+}
+
+impl fmt::Display for ClassificationReport {
+    /// Print the metrics of every class, the averages and the confusion matrix as tables
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        let name_width = self.class_names.iter().map(|n| n.len()).max().unwrap_or(0).max(9);
+
+        writeln!(f, "{:<w$} {:>9} {:>9} {:>9} {:>9}", "Class", "Precision", "Recall", "F1", "Support", w = name_width)?;
+        let rows = self.class_names.iter().map(|n| n.as_str()).zip(self.classes.iter())
+            .chain([("Macro avg", &self.macro_average), ("Micro avg", &self.micro_average)]);
+        for (name, metrics) in rows {
+            writeln!(f, "{:<w$} {:>9.4} {:>9.4} {:>9.4} {:>9}", name, metrics.precision, metrics.recall, metrics.f1, metrics.support, w = name_width)?;
+        }
+
+        writeln!(f)?;
+        write!(f, "Accuracy: {:.4}  Log-loss: {:.4}", self.accuracy, self.log_loss)?;
+        if let Some(auc) = self.roc_auc {
+            write!(f, "  ROC-AUC: {:.4}", auc)?;
+        }
+        writeln!(f)?;
+
+        writeln!(f)?;
+        writeln!(f, "Confusion matrix (rows are actual, columns predicted classes)")?;
+        let cell_width = self.class_names.iter().map(|n| n.len()).chain(self.confusion_matrix.iter().flatten().map(|c| c.to_string().len())).max().unwrap_or(0);
+        write!(f, "{:<w$}", "", w = name_width)?;
+        for name in &self.class_names {
+            write!(f, " {:>w$}", name, w = cell_width)?;
+        }
+        writeln!(f)?;
+        for (name, row) in self.class_names.iter().zip(&self.confusion_matrix) {
+            write!(f, "{:<w$}", name, w = name_width)?;
+            for count in row {
+                write!(f, " {:>w$}", count, w = cell_width)?;
+            }
+            writeln!(f)?;
+        }
+        return Ok(());
+    }
+}
+
+/// Class of an output or expected output vector
+fn class_of(values : &[f64]) -> usize {
+    if values.len() == 1 {
+        return (values[0] >= 0.5) as usize;
+    }
+    return super::index_of_max(values);
+}
+
+/// Cross-entropy of one sample, binary for a single output
+fn cross_entropy(output : &[f64], expected : &[f64]) -> f64 {
+    let clip = |p : f64| p.clamp(PROBABILITY_EPSILON, 1.0 - PROBABILITY_EPSILON);
+    if output.len() == 1 {
+        return -(expected[0] * clip(output[0]).ln() + (1.0 - expected[0]) * (1.0 - clip(output[0])).ln());
+    }
+    let mut loss = 0.0;
+    for i in 0..output.len() {
+        loss -= expected[i] * clip(output[i]).ln();
+    }
+    return loss;
+}
+
+/// Probability that a random positive sample scores higher than a random negative one, with ties counting half
+fn roc_auc(scores : &[(f64, bool)]) -> Option<f64> {
+    let positives = scores.iter().filter(|(_, positive)| *positive).count();
+    let negatives = scores.len() - positives;
+    if positives == 0 || negatives == 0 {
+        return None;
+    }
+
+    let mut sorted = scores.to_vec();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // Sum of the ranks of all positives, tied scores share their average rank
+    let mut rank_sum = 0.0;
+    let mut i = 0;
+    while i < sorted.len() {
+        let mut j = i;
+        while j < sorted.len() && sorted[j].0 == sorted[i].0 {
+            j += 1;
+        }
+        let average_rank = (i + j + 1) as f64 / 2.0;
+        rank_sum += average_rank * sorted[i..j].iter().filter(|(_, positive)| *positive).count() as f64;
+        i = j;
+    }
+
+    let positives = positives as f64;
+    return Some((rank_sum - positives * (positives + 1.0) / 2.0) / (positives * negatives as f64));
+}
+
+fn fraction(count : usize, total : usize) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    return count as f64 / total as f64;
+}
+
+fn mean<I: Iterator<Item = f64>>(values : I) -> f64 {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    if count == 0 {
+        return 0.0;
+    }
+    return sum / count as f64;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a : f64, b : f64) -> bool {
+        return (a - b).abs() < 1e-12;
+    }
+
+    /// Probabilities of 0.8 for the predicted class and 0.1 for the others
+    fn probabilities(class : usize) -> Vec<f64> {
+        return (0..3).map(|c| if c == class { 0.8 } else { 0.1 }).collect();
+    }
+
+    fn one_hot(class : usize) -> Vec<f64> {
+        return (0..3).map(|c| if c == class { 1.0 } else { 0.0 }).collect();
+    }
+
+    #[test]
+    fn three_classes() {
+        // Pairs of actual and predicted class
+        let pairs = [(0, 0), (0, 0), (0, 1), (1, 1), (1, 1), (1, 2), (2, 2), (2, 0)];
+        let outputs : Vec<Vec<f64>> = pairs.iter().map(|(_, predicted)| probabilities(*predicted)).collect();
+        let expected : Vec<Vec<f64>> = pairs.iter().map(|(actual, _)| one_hot(*actual)).collect();
+        let report = ClassificationReport::from_outputs(&outputs, &expected);
+
+        assert_eq!(report.confusion_matrix, vec![vec![2, 1, 0], vec![0, 2, 1], vec![1, 0, 1]]);
+        assert!(close(report.accuracy, 5.0 / 8.0));
+
+        // Classes 0 and 1 have 2 true positives, 1 false positive and 1 false negative, class 2 has one of each
+        for c in 0..2 {
+            assert!(close(report.classes[c].precision, 2.0 / 3.0) && close(report.classes[c].recall, 2.0 / 3.0) && close(report.classes[c].f1, 2.0 / 3.0));
+            assert_eq!(report.classes[c].support, 3);
+        }
+        assert!(close(report.classes[2].precision, 0.5) && close(report.classes[2].recall, 0.5) && close(report.classes[2].f1, 0.5));
+        assert_eq!(report.classes[2].support, 2);
+
+        assert!(close(report.macro_average.precision, 11.0 / 18.0));
+        assert!(close(report.macro_average.recall, 11.0 / 18.0));
+        assert!(close(report.macro_average.f1, 11.0 / 18.0));
+        assert!(close(report.micro_average.precision, 5.0 / 8.0) && close(report.micro_average.recall, 5.0 / 8.0) && close(report.micro_average.f1, 5.0 / 8.0));
+        assert_eq!(report.macro_average.support, 8);
+
+        assert!(close(report.log_loss, -(5.0 * 0.8f64.ln() + 3.0 * 0.1f64.ln()) / 8.0));
+        assert_eq!(report.roc_auc, None);
+    }
+
+    #[test]
+    fn binary_classifier_with_one_output() {
+        let outputs = vec![vec![0.9], vec![0.4], vec![0.4], vec![0.1]];
+        let expected = vec![vec![1.0], vec![1.0], vec![0.0], vec![0.0]];
+        let report = ClassificationReport::from_outputs(&outputs, &expected);
+
+        // 0.4 is below the threshold, so the second sample is a false negative
+        assert_eq!(report.confusion_matrix, vec![vec![2, 0], vec![1, 1]]);
+        assert!(close(report.classes[1].precision, 1.0) && close(report.classes[1].recall, 0.5));
+        // Three of the four positive and negative pairs are ordered correctly, the tied pair counts half
+        assert_eq!(report.roc_auc, Some(0.875));
+    }
+
+    #[test]
+    fn roc_auc_of_tied_and_single_class_scores() {
+        assert_eq!(roc_auc(&[(0.5, true), (0.5, false), (0.5, true), (0.5, false)]), Some(0.5));
+        assert_eq!(roc_auc(&[(0.2, false), (0.7, true), (0.9, true)]), Some(1.0));
+        assert_eq!(roc_auc(&[(0.2, true), (0.7, true)]), None);
+
+        // Two outputs use the score of the second one
+        let report = ClassificationReport::from_outputs(&[vec![0.8, 0.2], vec![0.3, 0.7]], &[vec![0.0, 1.0], vec![1.0, 0.0]]);
+        assert_eq!(report.roc_auc, Some(0.0));
+        assert_eq!(report.accuracy, 0.0);
+    }
+
+    #[test]
+    fn empty_test_set() {
+        let report = ClassificationReport::from_outputs(&[], &[]);
+        assert_eq!(report.accuracy, 0.0);
+        assert_eq!(report.log_loss, 0.0);
+        assert_eq!(report.roc_auc, None);
+    }
+}
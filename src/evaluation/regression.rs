@@ -0,0 +1,254 @@
+use std::fmt;
+
+use crate::network::{NetworkError, NeuralNetwork};
+use crate::trainer::TrainingData;
+
+/// Errors of one output of a regression model
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OutputMetrics {
+    /// Mean squared error
+    pub mse : f64,
+    /// Root of the mean squared error, in the units of the output
+    pub rmse : f64,
+    /// Mean absolute error
+    pub mae : f64,
+    /// Mean absolute percentage error, as a fraction. Samples with an expected value of 0 are left out, None if there are no others.
+    pub mape : Option<f64>,
+    /// Coefficient of determination. 1 for perfect predictions, 0 for always predicting the mean, negative for worse.
+    pub r2 : f64,
+    /// Like r2, but ignores a constant offset of all predictions
+    pub explained_variance : f64,
+    /// Largest absolute error of a single sample
+    pub max_error : f64
+}
+
+impl OutputMetrics {
+    fn from_values(predicted : &[f64], expected : &[f64]) -> OutputMetrics {
+        let n = expected.len();
+        if n == 0 {
+            return OutputMetrics::default();
+        }
+        let residuals : Vec<f64> = expected.iter().zip(predicted).map(|(e, p)| e - p).collect();
+
+        let mse = residuals.iter().map(|r| r * r).sum::<f64>() / n as f64;
+        let mae = residuals.iter().map(|r| r.abs()).sum::<f64>() / n as f64;
+        let max_error = residuals.iter().fold(0.0, |max : f64, r| max.max(r.abs()));
+
+        let percentages : Vec<f64> = (0..n).filter(|i| expected[*i] != 0.0).map(|i| (residuals[i] / expected[i]).abs()).collect();
+        let mape = if percentages.is_empty() { None } else { Some(percentages.iter().sum::<f64>() / percentages.len() as f64) };
+
+        let expected_variance = variance(expected);
+        let residual_mean = residuals.iter().sum::<f64>() / n as f64;
+        return OutputMetrics {
+            mse,
+            rmse: mse.sqrt(),
+            mae,
+            mape,
+            r2: explained_fraction(mse, expected_variance),
+            explained_variance: explained_fraction(mse - residual_mean * residual_mean, expected_variance),
+            max_error
+        };
+    }
+}
+
+/// Quality of a regression model on a test set, with the errors measured in the raw units of the expected outputs
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegressionReport {
+    /// Names of the outputs in the table, the output index by default
+    pub output_names : Vec<String>,
+    /// Metrics of every output
+    pub outputs : Vec<OutputMetrics>,
+    /// Expected minus predicted value, indexed [output][sample]
+    pub residuals : Vec<Vec<f64>>
+}
+
+/// Counts of values in equally wide bins
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    /// Bounds of the bins, one more than there are bins. The last bin includes its upper bound.
+    pub edges : Vec<f64>,
+    /// Number of values in every bin
+    pub counts : Vec<usize>
+}
+
+impl RegressionReport {
+    /// Run the network over a test set and measure the errors of its outputs.
+    /// Inputs and outputs are raw values, so models with different pipelines or training methods are compared on equal terms.
+    pub fn evaluate(network : &NeuralNetwork, test_set : &[TrainingData]) -> Result<RegressionReport, NetworkError> {
+        let mut outputs = vec![];
+        for ts in test_set {
+            let output = network.predict(ts.input())?;
+            if output.len() != ts.output().len() {
+                return Err(NetworkError::OutputSizeMismatch { expected: output.len(), actual: ts.output().len() });
+            }
+            outputs.push(output);
+        }
+        return Ok(RegressionReport::from_outputs(&outputs, &test_set.iter().map(|ts| ts.output().to_vec()).collect::<Vec<_>>()));
+    }
+
+    /// Create a report from predicted and expected values, one pair of vectors of the same size per sample
+    pub fn from_outputs(outputs : &[Vec<f64>], expected : &[Vec<f64>]) -> RegressionReport {
+        let width = outputs.first().or(expected.first()).map_or(0, |o| o.len());
+        let samples = outputs.len().min(expected.len());
+
+        let mut metrics = vec![];
+        let mut residuals = vec![];
+        for o in 0..width {
+            let predicted : Vec<f64> = (0..samples).map(|s| outputs[s][o]).collect();
+            let actual : Vec<f64> = (0..samples).map(|s| expected[s][o]).collect();
+            metrics.push(OutputMetrics::from_values(&predicted, &actual));
+            residuals.push(actual.iter().zip(&predicted).map(|(e, p)| e - p).collect());
+        }
+
+        return RegressionReport {
+            output_names: (0..width).map(|o| o.to_string()).collect(),
+            outputs: metrics,
+            residuals
+        };
+    }
+
+    /// Histogram of the residuals of one output, with the given number of bins between the smallest and largest residual
+    pub fn residual_histogram(&self, output : usize, bins : usize) -> Histogram {
+        return Histogram::new(&self.residuals[output], bins);
+    }
+}
+
+impl fmt::Display for RegressionReport {
+    /// Print the metrics of every output as a table
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        let name_width = self.output_names.iter().map(|n| n.len()).max().unwrap_or(0).max(6);
+        writeln!(f, "{:<w$} {:>12} {:>12} {:>12} {:>9} {:>9} {:>9} {:>12}", "Output", "MSE", "RMSE", "MAE", "MAPE", "R2", "Expl. var", "Max error", w = name_width)?;
+        for (name, m) in self.output_names.iter().zip(&self.outputs) {
+            let mape = match m.mape {
+                Some(mape) => format!("{:.2}%", mape * 100.0),
+                None => "-".to_string()
+            };
+            writeln!(f, "{:<w$} {:>12.6} {:>12.6} {:>12.6} {:>9} {:>9.4} {:>9.4} {:>12.6}", name, m.mse, m.rmse, m.mae, mape, m.r2, m.explained_variance, m.max_error, w = name_width)?;
+        }
+        return Ok(());
+    }
+}
+
+impl Histogram {
+    /// Sort the values into the given number of equally wide bins between the smallest and largest value.
+    /// Non-finite values are left out.
+    pub fn new(values : &[f64], bins : usize) -> Histogram {
+        let finite : Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+        let bins = bins.max(1);
+        if finite.is_empty() {
+            return Histogram { edges: vec![0.0; bins + 1], counts: vec![0; bins] };
+        }
+
+        let min = finite.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = finite.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let width = (max - min) / bins as f64;
+        let edges = (0..=bins).map(|b| if b == bins { max } else { min + width * b as f64 }).collect();
+
+        let mut counts = vec![0; bins];
+        for v in finite {
+            let bin = if width > 0.0 { ((v - min) / width) as usize } else { 0 };
+            counts[bin.min(bins - 1)] += 1;
+        }
+        return Histogram { edges, counts };
+    }
+}
+
+impl fmt::Display for Histogram {
+    /// Print one line per bin with its range, count and a bar scaled to the largest count
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        let largest = self.counts.iter().copied().max().unwrap_or(0).max(1);
+        for b in 0..self.counts.len() {
+            let bar = "#".repeat((self.counts[b] * 40).div_ceil(largest));
+            writeln!(f, "[{:>12.4}, {:>12.4}{} {:>6} {}", self.edges[b], self.edges[b+1], if b + 1 == self.counts.len() { "]" } else { ")" }, self.counts[b], bar)?;
+        }
+        return Ok(());
+    }
+}
+
+fn variance(values : &[f64]) -> f64 {
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    return values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+}
+
+/// 1 - unexplained / total variance. Constant expected values give 1 for perfect predictions and 0 otherwise.
+fn explained_fraction(unexplained : f64, total : f64) -> f64 {
+    if total > 0.0 {
+        return 1.0 - unexplained / total;
+    }
+    if unexplained.abs() < f64::EPSILON {
+        return 1.0;
+    }
+    return 0.0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a : f64, b : f64) -> bool {
+        return (a - b).abs() < 1e-12;
+    }
+
+    /// Metrics of one output from predicted and expected values
+    fn metrics(predicted : &[f64], expected : &[f64]) -> OutputMetrics {
+        let outputs : Vec<Vec<f64>> = predicted.iter().map(|p| vec![*p]).collect();
+        let expected : Vec<Vec<f64>> = expected.iter().map(|e| vec![*e]).collect();
+        return RegressionReport::from_outputs(&outputs, &expected).outputs[0];
+    }
+
+    #[test]
+    fn errors_of_one_output() {
+        // Residuals are -1, 0, 0 and 3
+        let m = metrics(&[2.0, 2.0, 3.0, 1.0], &[1.0, 2.0, 3.0, 4.0]);
+        assert!(close(m.mse, 2.5));
+        assert!(close(m.rmse, 2.5f64.sqrt()));
+        assert!(close(m.mae, 1.0));
+        assert!(close(m.max_error, 3.0));
+        assert!(close(m.mape.unwrap(), (1.0 + 0.75) / 4.0));
+
+        // Expected values of 0 are left out of the MAPE
+        assert_eq!(metrics(&[1.0, 1.0], &[0.0, 2.0]).mape, Some(0.5));
+        assert_eq!(metrics(&[1.0], &[0.0]).mape, None);
+    }
+
+    #[test]
+    fn explained_variance_and_r2() {
+        let expected = [1.0, 2.0, 3.0, 4.0];
+
+        let perfect = metrics(&expected, &expected);
+        assert_eq!((perfect.r2, perfect.explained_variance, perfect.mse), (1.0, 1.0, 0.0));
+
+        let mean = metrics(&[2.5; 4], &expected);
+        assert!(close(mean.r2, 0.0) && close(mean.explained_variance, 0.0));
+
+        // A constant offset of 1 costs R² its share of the variance of 1.25, but not the explained variance
+        let offset = metrics(&[2.0, 3.0, 4.0, 5.0], &expected);
+        assert!(close(offset.r2, 0.2));
+        assert!(close(offset.explained_variance, 1.0));
+
+        let constant = metrics(&[1.0, 2.0], &[3.0, 3.0]);
+        assert_eq!((constant.r2, constant.explained_variance), (0.0, 0.0));
+    }
+
+    #[test]
+    fn residuals_of_several_outputs() {
+        let report = RegressionReport::from_outputs(&[vec![1.0, 10.0], vec![2.0, 20.0]], &[vec![1.5, 10.0], vec![1.0, 26.0]]);
+        assert_eq!(report.output_names, vec!["0", "1"]);
+        assert_eq!(report.residuals, vec![vec![0.5, -1.0], vec![0.0, 6.0]]);
+        assert!(close(report.outputs[1].mae, 3.0));
+    }
+
+    #[test]
+    fn histogram_bins() {
+        let histogram = Histogram::new(&[0.0, 1.0, 2.0, 3.0, 4.0, f64::NAN], 2);
+        assert_eq!(histogram.edges, vec![0.0, 2.0, 4.0]);
+        // The upper bound of the last bin is included, the non-finite value is left out
+        assert_eq!(histogram.counts, vec![2, 3]);
+
+        assert_eq!(Histogram::new(&[1.0, 1.0, 1.0], 3).counts, vec![3, 0, 0]);
+        assert_eq!(Histogram::new(&[], 4).counts, vec![0; 4]);
+
+        let report = RegressionReport::from_outputs(&[vec![0.0], vec![0.0], vec![0.0]], &[vec![-1.0], vec![0.0], vec![3.0]]);
+        assert_eq!(report.residual_histogram(0, 4).counts, vec![1, 1, 0, 1]);
+    }
+}
@@ -1,46 +1,86 @@
-use rand::Rng;
+use rand::{Rng, StdRng};
+use std::path::Path;
 
 /// Run example 1
 /// Network learns to invert two inputs using a set of training data
-fn run_example_one(trainer : &mut super::trainer::Trainer) -> super::trainer::TrainingResult {
-    let network = super::network::NeuralNetwork::new(vec![2,10,10,2]); // Create a network with a given size for the trainer
+pub fn run_example_one(trainer : &mut super::trainer::Trainer) -> Result<super::trainer::TrainingResult, super::network::NetworkError> {
+    // Create a network with a given size for the trainer, starting from random weights so the hidden nodes can learn different features
+    let network = super::network::NeuralNetwork::with_initializer(vec![2,10,10,2], vec![super::network::Activation::Sigmoid; 3], super::network::Initializer::XavierUniform, 1)?;
     // Create training data
-    let mut training_set : Vec<super::trainer::TrainingData> = vec![];
-    training_set.push(super::trainer::TrainingData::new(vec![0.0, 0.0], vec![1.0, 1.0]));
-    training_set.push(super::trainer::TrainingData::new(vec![1.0, 1.0], vec![0.0, 0.0]));
-    training_set.push(super::trainer::TrainingData::new(vec![0.0, 1.0], vec![1.0, 0.0]));
-    training_set.push(super::trainer::TrainingData::new(vec![1.0, 0.0], vec![0.0, 1.0]));
+    let training_set : Vec<super::trainer::TrainingData> = vec![
+        super::trainer::TrainingData::new(vec![0.0, 0.0], vec![1.0, 1.0]),
+        super::trainer::TrainingData::new(vec![1.0, 1.0], vec![0.0, 0.0]),
+        super::trainer::TrainingData::new(vec![0.0, 1.0], vec![1.0, 0.0]),
+        super::trainer::TrainingData::new(vec![1.0, 0.0], vec![0.0, 1.0]),
+    ];
     // Run network and save the learning curve in a variable 
     return trainer.train_genetic_algorithm_dataset(&network, &training_set, 200, 100, 1.0, 0.98); 
 }
 
+/// Run example 1 with cross-validation
+/// Trains the network of example 1 four times, each time leaving out one of the four samples and testing on it.
+/// Shows how much the result depends on the data the network was trained on.
+pub fn run_example_one_cross_validated(trainer : &mut super::trainer::Trainer) -> Result<super::trainer::CrossValidationResult, super::network::NetworkError> {
+    let network = super::network::NeuralNetwork::with_initializer(vec![2,10,10,2], vec![super::network::Activation::Sigmoid; 3], super::network::Initializer::XavierUniform, 1)?;
+    let training_set : Vec<super::trainer::TrainingData> = vec![
+        super::trainer::TrainingData::new(vec![0.0, 0.0], vec![1.0, 1.0]),
+        super::trainer::TrainingData::new(vec![1.0, 1.0], vec![0.0, 0.0]),
+        super::trainer::TrainingData::new(vec![0.0, 1.0], vec![1.0, 0.0]),
+        super::trainer::TrainingData::new(vec![1.0, 0.0], vec![0.0, 1.0]),
+    ];
+    let config = super::trainer::CrossValidationConfig { folds: 4, ..super::trainer::CrossValidationConfig::default() };
+    let result = trainer.cross_validate(&network, &training_set, &config, &mut |trainer, network, training_folds| trainer.train_genetic_algorithm_dataset(network, training_folds, 200, 100, 1.0, 0.98))?;
+    println!("{}", result);
+    return Ok(result);
+}
+
+/// Run the handwritten digit example
+/// Network learns to classify the 28x28 images of the MNIST dataset, or Fashion-MNIST which has the same layout.
+/// The directory must contain the four uncompressed IDX files under their original names.
+/// Returns the training result and the fraction of test images that were classified correctly.
+pub fn run_example_mnist(trainer : &mut super::trainer::Trainer, directory : &Path) -> Result<(super::trainer::TrainingResult, f64), Box<dyn std::error::Error>> {
+    let training_set = super::dataset::Dataset::from_idx(directory.join("train-images-idx3-ubyte"), directory.join("train-labels-idx1-ubyte"), 10)?;
+    let test_set = super::dataset::Dataset::from_idx(directory.join("t10k-images-idx3-ubyte"), directory.join("t10k-labels-idx1-ubyte"), 10)?;
+
+    // One hidden relu layer and a softmax output, which gives the probability of each digit
+    let network = super::network::NeuralNetwork::with_initializer(vec![784,128,10], vec![super::network::Activation::Relu, super::network::Activation::Softmax], super::network::Initializer::HeUniform, 1)?;
+    let config = super::trainer::GradientConfig {
+        epochs: 5,
+        loss: super::trainer::Loss::CategoricalCrossEntropy,
+        batch_size: super::trainer::BatchSize::Mini(64),
+        ..super::trainer::GradientConfig::default()
+    };
+    let result = trainer.train_with_optimizer(&network, training_set.samples(), &[], &config, &mut super::trainer::Adam::new(0.001))?;
+
+    let report = super::evaluation::ClassificationReport::evaluate(&result.network, test_set.samples())?;
+    println!("{}", report);
+    return Ok((result, report.accuracy));
+}
+
 /// Run example 2
 /// Network learns to replicate the inputs in its outputs, using a custom evaluation function that returns a score by which the networks are compared. 
-pub fn run_example_two(trainer : &mut super::trainer::Trainer) -> super::trainer::TrainingResult {
-    let network = super::network::NeuralNetwork::new(vec![2,2]); // Create a network with a given size for the trainer
+pub fn run_example_two(trainer : &mut super::trainer::Trainer) -> Result<super::trainer::TrainingResult, super::network::NetworkError> {
+    let network = super::network::NeuralNetwork::new(vec![2,2])?; // Create a network with a given size for the trainer
     let training_set : Vec<super::trainer::TrainingData> = vec![]; // We dont need a training set, because we can calculate the expected output from the input
     // Run network and save the learning curve in a variable 
     return trainer.train_genetic_algorithm_custom(&network, &training_set, 200, 300, 1.0, 0.98, &mut evaluate_copy_input); 
 }
 
-/// Evaluate the given network based on how simmilar the outputs are to the inputs and return the score
-pub fn evaluate_copy_input(training_set : &Vec<super::trainer::TrainingData>, network : &mut super::network::NeuralNetwork) -> f64{
+/// Evaluate the given network based on how simmilar the outputs are to the inputs and return the score.
+/// The inputs are drawn from the given random number generator. Fails if the output layer does not have the size of the input layer.
+pub fn evaluate_copy_input(_training_set : &[super::trainer::TrainingData], network : &mut super::network::NeuralNetwork, rng : &mut StdRng) -> Result<f64, super::network::NetworkError> {
     if network.nodes[0].len() != network.nodes[network.nodes.len()-1].len() {
-        println!("{}", "Error: Input layer does not match output layer!");
-        return 0.0;
+        return Err(super::network::NetworkError::OutputSizeMismatch { expected: network.nodes[network.nodes.len()-1].len(), actual: network.nodes[0].len() });
     }
 
     let mut score :f64 = 0.0;
 
-    for g in 0..20 {
-        let mut rng = rand::thread_rng(); // Create random generator instance
+    for _g in 0..20 {
         let mut input = vec![];
-        for i in 0..network.nodes[0].len() {
+        for _i in 0..network.nodes[0].len() {
             input.push(rng.gen_range(0.0, 1.0));
         }
-        network.set_inputs(input.clone());
-        network.calculate_network();
-        let output = network.get_outputs();
+        let output = network.predict(&input)?;
 
         let mut difference : f64 = 0.0;
         for i in 0..output.len() {
@@ -49,12 +89,12 @@ pub fn evaluate_copy_input(training_set : &Vec<super::trainer::TrainingData>, ne
         score -= difference;
     }
     
-    return score;
+    return Ok(score);
 }
 
 /// Run example 2
 /// Shows how to train two networks using a GAN (Generative adversarial network)
-pub fn run_example_three(trainer : &mut super::trainer::Trainer) {
+pub fn run_example_three(_trainer : &mut super::trainer::Trainer) {
     // Define set of training data for the second network to train on
     // Call trainer method that repeats this for x iterations: 
     // Give the second net real data, evaluate the result and modify it using something like back propagation
@@ -0,0 +1,20 @@
+//! Neural networks for simple usage, with training by genetic algorithms or backpropagation.
+//!
+//! The network viewer is a separate binary behind the `viewer` feature, so the library
+//! can be used without piston and OpenGL by disabling the default features.
+
+// Explicit returns and index based loops are the style of this project
+#![allow(clippy::needless_return, clippy::needless_range_loop)]
+
+pub mod network;
+pub mod trainer;
+pub mod examples;
+pub mod dataset;
+pub mod evaluation;
+
+pub use dataset::{Column, CsvOptions, Dataset, DatasetError, IdxArray, MissingValues};
+pub use evaluation::{ClassMetrics, ClassificationReport, Histogram, OutputMetrics, RegressionReport};
+pub use network::{Activation, Initializer, NetworkError, NeuralNetwork, Pipeline, PreprocessingStep, Preprocessor, StorageError, StorageFormat};
+// Random number generator types used by initialization, mutation and evaluation functions
+pub use rand::{Rng, SeedableRng, StdRng};
+pub use trainer::{BatchSize, CallbackAction, CrossValidationConfig, CrossValidationMetrics, CrossValidationResult, Crossover, EvaluationFunction, GeneticConfig, GradientConfig, Loss, Optimizer, ParallelEvaluationFunction, ProgressPrinter, Schedule, Selection, Trainer, TrainingCallback, TrainingData, TrainingResult, ValidationConfig};
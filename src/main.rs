@@ -1,6 +1,7 @@
-pub mod network;
-mod trainer;
-mod examples;
+// Explicit returns and index based loops are the style of this project
+#![allow(clippy::needless_return, clippy::needless_range_loop)]
+
+use neural_net::{examples, network, trainer};
 
 extern crate glutin_window;
 extern crate graphics;
@@ -14,11 +15,14 @@ use opengl_graphics::{GlGraphics, OpenGL};
 // Import piston engine for visuals
 use piston::{ButtonEvent, RenderEvent};
 use piston::event_loop::{EventSettings, Events};
-use piston::input::{RenderArgs, UpdateArgs, UpdateEvent, Button, ButtonState, Key, MouseCursorEvent};
+use piston::input::{RenderArgs, UpdateArgs, UpdateEvent, Button, ButtonState, Key};
 use piston::window::WindowSettings;
 
 use rand::Rng;
 
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread;
+
 // Globals:
 static COL_BACKGROUND: [f32; 4] = [0.2, 0.2, 0.2, 1.0];
 static COL_NODEVIEW_BACKGROUND: [f32; 4] = [0.16, 0.16, 0.16, 1.0];
@@ -26,8 +30,95 @@ static COL_NODEVIEW_BACKGROUND: [f32; 4] = [0.16, 0.16, 0.16, 1.0];
 struct App {
     gl: GlGraphics, // OpenGL drawing backend.
     network : network::NeuralNetwork, // The current network
-    trainer : trainer::Trainer, // Network Trainer
-    score_curve : Vec<f64> // List of score values, generated when traineing. Used for displaying learning curve
+    score_curve : Vec<f64>, // List of score values, generated when traineing. Used for displaying learning curve
+    receiver : Option<mpsc::Receiver<TrainingMessage>>, // Snapshots sent by the training thread
+    control : Arc<TrainingControl>, // Pause, step and restart the training thread
+    worker : Option<thread::JoinHandle<()>> // Thread running the training
+}
+
+/// Sent from the training thread to the viewer
+enum TrainingMessage {
+    /// A generation finished. Contains its index, the score and the best network of the generation.
+    Generation(usize, f64, network::NeuralNetwork),
+    /// Training finished with the returned network and score curve
+    Finished(network::NeuralNetwork, Vec<f64>),
+    Failed(String)
+}
+
+/// State of the training thread, changed by the keyboard controls of the viewer
+#[derive(Default)]
+struct ControlState {
+    paused : bool,
+    /// Number of generations to run while paused
+    steps : usize,
+    restart : bool,
+    quit : bool
+}
+
+/// Control state shared between the viewer and the training thread
+#[derive(Default)]
+struct TrainingControl {
+    state : Mutex<ControlState>,
+    changed : Condvar
+}
+
+impl TrainingControl {
+    /// Change the state and wake up the training thread
+    fn update<F: FnOnce(&mut ControlState)>(&self, change : F) {
+        change(&mut self.state.lock().unwrap());
+        self.changed.notify_all();
+    }
+
+    /// Block while training is paused. Returns false if the current run should stop.
+    fn wait_for_next_generation(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.restart || state.quit {
+                return false;
+            }
+            if !state.paused {
+                return true;
+            }
+            if state.steps > 0 {
+                state.steps -= 1;
+                return true;
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    /// Block after a run ended until training is restarted. Returns false if the viewer was closed.
+    fn wait_for_restart(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.quit {
+                return false;
+            }
+            if state.restart {
+                state.restart = false;
+                return true;
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+}
+
+/// Training callback that sends every generation to the viewer and waits while training is paused
+struct LiveView {
+    sender : mpsc::Sender<TrainingMessage>,
+    control : Arc<TrainingControl>
+}
+
+impl trainer::TrainingCallback for LiveView {
+    fn on_generation_end(&mut self, info : &trainer::GenerationInfo) -> trainer::CallbackAction {
+        if self.sender.send(TrainingMessage::Generation(info.generation, info.score, info.network.clone())).is_err() {
+            return trainer::CallbackAction::Stop;
+        }
+        if self.control.wait_for_next_generation() {
+            return trainer::CallbackAction::Continue;
+        }
+        return trainer::CallbackAction::Stop;
+    }
 }
 
 
@@ -65,9 +156,9 @@ impl App {
                     // Draw weights as lines
                     if l != current_net.nodes.len()-1 {
                         for nn in 0..current_net.nodes[l+1].len() {
-                            line([current_net.weights[l][n][nn] as f32,
-                                current_net.weights[l][n][nn] as f32,
-                                current_net.weights[l][n][nn] as f32,
+                            line([current_net.weights[l][(n, nn)] as f32,
+                                current_net.weights[l][(n, nn)] as f32,
+                                current_net.weights[l][(n, nn)] as f32,
                                 1.0], 0.4, [
                                 50.0+(l as f64/(current_net.nodes.len() as f64)) * 400.0 + 5.0,
                                 50.0+n as f64/(max_layer_size as f64) * 400.0 + 5.0,
@@ -91,18 +182,18 @@ impl App {
             rectangle(COL_NODEVIEW_BACKGROUND, rectangle::square(0.0, 0.0, 300.0), score_view_transform.trans(0.0,-25.0),gl);
 
             // Draw learning curve
-            for i in 0..current_score_curve.len()-1 {
+            for i in 0..current_score_curve.len().saturating_sub(1) {
                 // Draw lines between generation scores
                 line([1.0; 4], 0.4, [
                     (i as f64 * 300.0) / current_score_curve.len() as f64,
-                    -current_score_curve[i] as f64 * 100.0,
+                    -current_score_curve[i] * 100.0,
                     ((i+1) as f64 * 300.0) / current_score_curve.len() as f64,
-                    -current_score_curve[i+1] as f64 * 100.0
+                    -current_score_curve[i+1] * 100.0
                 ], score_view_transform, gl);
 
                 // Draw points at each generation point
                 rectangle([1.0, 0.0, 0.0, 1.0], rectangle::square(0.0, 0.0, 2.0), 
-                          score_view_transform.trans((i as f64 * 300.0) / current_score_curve.len() as f64, -current_score_curve[i] as f64 * 100.0).
+                          score_view_transform.trans((i as f64 * 300.0) / current_score_curve.len() as f64, -current_score_curve[i] * 100.0).
                           trans(-1.0, -1.0), gl);
             }
 
@@ -116,16 +207,58 @@ impl App {
         });
     }
 
-    /// Internal update loop
+    /// Internal update loop. Shows the snapshots sent by the training thread since the last update.
     fn update(&mut self, _args: &UpdateArgs) {
-        
+        let receiver = match &self.receiver {
+            Some(receiver) => receiver,
+            None => return
+        };
+        while let Ok(message) = receiver.try_recv() {
+            match message {
+                TrainingMessage::Generation(generation, score, network) => {
+                    // A new run starts with an empty curve
+                    if generation == 0 {
+                        self.score_curve.clear();
+                    }
+                    self.score_curve.push(score);
+                    self.network = network;
+                },
+                TrainingMessage::Finished(network, score_curve) => {
+                    self.network = network;
+                    self.score_curve = score_curve;
+                },
+                TrainingMessage::Failed(e) => eprintln!("Training failed: {}", e)
+            }
+        }
     }
 
-    /// Called once before the first update loop
+    /// Called once before the first update loop. Starts training on a separate thread.
     fn start(&mut self) {
-        let result = examples::run_example_two(&mut self.trainer);
-        self.network = result.network;
-        self.score_curve = result.generation_score_curve;
+        let (sender, receiver) = mpsc::channel();
+        let control = self.control.clone();
+        self.receiver = Some(receiver);
+        self.worker = Some(thread::spawn(move || {
+            loop {
+                let mut trainer = trainer::Trainer::new();
+                trainer.add_callback(Box::new(trainer::ProgressPrinter::new()));
+                trainer.add_callback(Box::new(LiveView { sender: sender.clone(), control: control.clone() }));
+                let message = match examples::run_example_two(&mut trainer) {
+                    Ok(result) => TrainingMessage::Finished(result.network, result.generation_score_curve),
+                    Err(e) => TrainingMessage::Failed(e.to_string())
+                };
+                if sender.send(message).is_err() || !control.wait_for_restart() {
+                    return;
+                }
+            }
+        }));
+    }
+
+    /// Called once after the window was closed. Stops the training thread.
+    fn stop(&mut self) {
+        self.control.update(|state| state.quit = true);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
     }
 }
 
@@ -151,11 +284,14 @@ fn initialize_graphics() {
     // Create a new game and run it.
     let mut app = App {
         gl: GlGraphics::new(opengl),
-        network : network::NeuralNetwork::new(vec![2,2]),
-        trainer: trainer::Trainer::new(),
-        score_curve : vec![]
+        network : network::NeuralNetwork::new(vec![2,2]).unwrap(),
+        score_curve : vec![],
+        receiver : None,
+        control : Arc::new(TrainingControl::default()),
+        worker : None
     };
 
+    println!("Controls: P pause / resume, N next generation, R restart training, Space run random inputs, S save network");
     app.start();
     
     let mut events = Events::new(EventSettings::new());
@@ -173,12 +309,33 @@ fn initialize_graphics() {
                 match k.button {
                     Button::Keyboard(Key::Space) => {
                         let mut rng = rand::thread_rng();
-                        app.network.set_inputs(vec![rng.gen_range(-1.0, 1.0), rng.gen_range(-1.0, 1.0)]);
-                        app.network.calculate_network();
+                        let input = (0..app.network.nodes[0].len()).map(|_| rng.gen_range(-1.0, 1.0)).collect();
+                        match app.network.set_inputs(input) {
+                            Ok(()) => app.network.calculate_network(),
+                            Err(e) => eprintln!("{}", e)
+                        }
                     },
+                    Button::Keyboard(Key::S) => {
+                        // Keep the trained network after the viewer is closed
+                        match app.network.save("network.json", network::StorageFormat::Json) {
+                            Ok(()) => println!("Saved network to network.json"),
+                            Err(e) => eprintln!("{}", e)
+                        }
+                    },
+                    Button::Keyboard(Key::P) => app.control.update(|state| state.paused = !state.paused),
+                    Button::Keyboard(Key::N) => app.control.update(|state| {
+                        state.paused = true;
+                        state.steps += 1;
+                    }),
+                    Button::Keyboard(Key::R) => app.control.update(|state| {
+                        state.restart = true;
+                        state.steps = 0;
+                    }),
                     _ => (),
                 }
             } 
         }
     }
+
+    app.stop();
 }
\ No newline at end of file
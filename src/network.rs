@@ -1,133 +1,322 @@
-use rand::Rng;
+use rand::{Rng, SeedableRng, StdRng};
+use std::borrow::Cow;
 
+use crate::trainer::TrainingData;
+
+pub mod activation;
+pub mod batch;
+pub mod error;
+pub mod initializer;
+pub mod math;
+pub mod preprocessing;
+pub mod storage;
+
+pub use self::activation::Activation;
+pub use self::batch::BatchWorkspace;
+pub use self::error::NetworkError;
+pub use self::initializer::Initializer;
+pub use self::math::{Matrix, Vector};
+pub use self::preprocessing::{Pipeline, PreprocessingStep, Preprocessor};
+pub use self::storage::{StorageError, StorageFormat};
+
+#[derive(Clone)]
 pub struct NeuralNetwork {
     pub nodes : Vec<Vec<f64>>,
-    pub weights : Vec<Vec<Vec<f64>>>,
-    pub biases : Vec<Vec<f64>>
+    pub weighted_sums : Vec<Vec<f64>>,
+    /// Weights between every layer and the next, one row per node of the layer and one column per node of the next layer
+    pub weights : Vec<Matrix>,
+    pub biases : Vec<Vec<f64>>,
+    /// Activation function of every layer after the input layer
+    pub activations : Vec<Activation>,
+    /// Turns raw inputs into the values of the input layer
+    pub input_pipeline : Pipeline,
+    /// Turns raw expected outputs into the values of the output layer. Outputs are turned back into raw values by its inverse.
+    pub output_pipeline : Pipeline
 }
 
 impl NeuralNetwork {
-    /// Create a new Neural-Network with a given structure
-    pub fn new(structure : Vec<usize>) -> NeuralNetwork{
+    /// Create a new Neural-Network with a given structure, see initialize for the starting weights
+    pub fn new(structure : Vec<usize>) -> Result<NeuralNetwork, NetworkError> {
         let mut net = NeuralNetwork{
             nodes: vec![],
+            weighted_sums: vec![],
             weights: vec![],
-            biases: vec![]
+            biases: vec![],
+            activations: vec![],
+            input_pipeline: Pipeline::new(),
+            output_pipeline: Pipeline::new()
         };
 
-        net.initialize(structure);
+        net.initialize(structure)?;
+
+        return Ok(net);
+    }
+
+    /// Create a new Neural-Network with a given structure and one activation function for every layer after the input layer
+    pub fn with_activations(structure : Vec<usize>, activations : Vec<Activation>) -> Result<NeuralNetwork, NetworkError> {
+        let mut net = NeuralNetwork::new(structure)?;
+        net.set_activations(activations)?;
+        return Ok(net);
+    }
 
-        return net;
+    /// Create a new Neural-Network with a given structure and activation functions,
+    /// with the weights chosen by the initializer using a random generator seeded with seed
+    pub fn with_initializer(structure : Vec<usize>, activations : Vec<Activation>, initializer : Initializer, seed : u64) -> Result<NeuralNetwork, NetworkError> {
+        let mut net = NeuralNetwork::with_activations(structure, activations)?;
+        let mut rng = StdRng::from_seed(&[seed as usize]);
+        net.initialize_parameters(&initializer, &mut rng);
+        return Ok(net);
     }
 
-    /// Set the weigts of this network
-    pub fn set_weights(&mut self, new_weights : Vec<Vec<Vec<f64>>>) {
+    /// Replace the weights of this network with new ones chosen by the initializer and reset all biases to zero
+    pub fn initialize_parameters<R: Rng>(&mut self, initializer : &Initializer, rng : &mut R) {
+        for l in 0..self.weights.len() {
+            self.weights[l] = initializer.initialize_weights(self.nodes[l].len(), self.nodes[l+1].len(), rng);
+        }
+        for l in 0..self.biases.len() {
+            self.biases[l] = vec![0.0; self.nodes[l].len()];
+        }
+    }
+
+    /// Set the activation functions of this network, one for every layer after the input layer
+    pub fn set_activations(&mut self, new_activations : Vec<Activation>) -> Result<(), NetworkError> {
+        if new_activations.len() != self.nodes.len()-1 {
+            return Err(NetworkError::LayerCountMismatch { expected: self.nodes.len()-1, actual: new_activations.len() });
+        }
+        self.activations = new_activations;
+        return Ok(());
+    }
+
+    /// Set the activation function of the layer at layer_index. The input layer has no activation function.
+    pub fn set_activation(&mut self, layer_index : usize, activation : Activation) -> Result<(), NetworkError> {
+        if layer_index == 0 || layer_index >= self.nodes.len() {
+            return Err(NetworkError::LayerIndexOutOfRange { index: layer_index, layers: self.nodes.len() });
+        }
+        self.activations[layer_index-1] = activation;
+        return Ok(());
+    }
+
+    /// Set the weigts of this network. There must be one matrix for every pair of neighbouring layers, matching their sizes.
+    pub fn set_weights(&mut self, new_weights : Vec<Matrix>) -> Result<(), NetworkError> {
+        if new_weights.len() != self.nodes.len()-1 {
+            return Err(NetworkError::LayerCountMismatch { expected: self.nodes.len()-1, actual: new_weights.len() });
+        }
+        for l in 0..new_weights.len() {
+            let expected = (self.nodes[l].len(), self.nodes[l+1].len());
+            let actual = (new_weights[l].rows(), new_weights[l].columns());
+            if expected != actual {
+                return Err(NetworkError::InconsistentWeights { layer: l, expected, actual });
+            }
+            NeuralNetwork::check_finite(new_weights[l].data(), "weight")?;
+        }
         self.weights = new_weights;
+        return Ok(());
     }
 
-    /// Set the biases of this network
-    pub fn set_biases(&mut self, new_biases : Vec<Vec<f64>>) {
-        self.biases = vec![];
+    /// Set the biases of this network. There must be one bias for every node, including the unused ones of the input layer.
+    pub fn set_biases(&mut self, new_biases : Vec<Vec<f64>>) -> Result<(), NetworkError> {
+        if new_biases.len() != self.nodes.len() {
+            return Err(NetworkError::LayerCountMismatch { expected: self.nodes.len(), actual: new_biases.len() });
+        }
         for l in 0..new_biases.len() {
-            self.biases.push(vec![]);
-            for n in 0..new_biases[l].len() {
-                self.biases[l].push(new_biases[l][n].clone());
+            if new_biases[l].len() != self.nodes[l].len() {
+                return Err(NetworkError::InconsistentBiases { layer: l, expected: self.nodes[l].len(), actual: new_biases[l].len() });
             }
+            NeuralNetwork::check_finite(&new_biases[l], "bias")?;
         }
+        self.biases = new_biases;
+        return Ok(());
+    }
+
+    // Set the input layer of this network from raw inputs, which are preprocessed by the input pipeline
+    pub fn set_inputs(&mut self, input : Vec<f64>) -> Result<(), NetworkError> {
+        let input = self.preprocess_input(&input)?.into_owned();
+        self.check_input(&input)?;
+        self.nodes[0] = input;
+        return Ok(());
     }
 
-    // Set the input layer of this network
-    pub fn set_inputs(&mut self, input : Vec<f64>) {
+    /// Check that an input vector fits the input layer and only contains finite values
+    pub fn check_input(&self, input : &[f64]) -> Result<(), NetworkError> {
         if input.len() != self.nodes[0].len() {
-            eprintln!("{0}: {1} -> {2}", "Input vector does not match the length of the input layer.", input.len(), self.nodes[0].len());
+            return Err(NetworkError::InputSizeMismatch { expected: self.nodes[0].len(), actual: input.len() });
+        }
+        return NeuralNetwork::check_finite(input, "input");
+    }
+
+    /// Check that an expected output vector fits the output layer and only contains finite values
+    pub fn check_output(&self, output : &[f64]) -> Result<(), NetworkError> {
+        let output_size = self.nodes[self.nodes.len()-1].len();
+        if output.len() != output_size {
+            return Err(NetworkError::OutputSizeMismatch { expected: output_size, actual: output.len() });
+        }
+        return NeuralNetwork::check_finite(output, "output");
+    }
+
+    fn check_finite(values : &[f64], what : &'static str) -> Result<(), NetworkError> {
+        if values.iter().all(|v| v.is_finite()) {
+            return Ok(());
         }
-        self.nodes[0] = input.clone();
+        return Err(NetworkError::NonFiniteValue(what));
     }
 
-    /// Get the outputs of the network as a vector of the node values in the output layers
+    /// Get the outputs of the network as a vector of the node values in the output layers, turned back into raw values by the output pipeline
     /// This does not run the calculate_network method. It must be run before this. 
     pub fn get_outputs(&mut self) -> Vec<f64> {
         //println!("Len: {}", self.nodes.len());
-        return self.nodes[self.nodes.len()-1].clone();
+        return self.output_pipeline.inverse_transform(&self.nodes[self.nodes.len()-1]);
     }
 
-    /// Initialize this network with a given structure
-    pub fn initialize(&mut self, structure : Vec<usize>) {
-        //println!("Initializing with structure: {0} -> {1}, ...", structure.len(), structure[0]);
+    /// Initialize this network with a given structure. All layers use the sigmoid activation function and all biases are zero.
+    /// The weights are Xavier uniform with seed 0, so every network of the same structure starts the same and training can be reproduced,
+    /// while the nodes of a layer still learn different things. Use initialize_parameters to choose other starting weights.
+    pub fn initialize(&mut self, structure : Vec<usize>) -> Result<(), NetworkError> {
+        if structure.len() < 2 || structure.contains(&0) {
+            return Err(NetworkError::EmptyStructure);
+        }
 
         self.nodes = vec![];
         for l in 0..structure.len() {
             self.nodes.push(vec![0.0; structure[l]]);
         }
+        self.weighted_sums = self.nodes.clone();
+        self.activations = vec![Activation::Sigmoid; structure.len()-1];
 
         self.weights = vec![];
         for l in 0..structure.len()-1 {
-            self.weights.push(vec![vec![]; structure[l]]);
-            for n in 0..structure[l] {
-                self.weights[l][n] = vec![1.0; structure[l+1]];
-            }
+            self.weights.push(Matrix::new(structure[l], structure[l+1]));
         }
+        self.biases = vec![vec![]; structure.len()];
+        self.initialize_parameters(&Initializer::XavierUniform, &mut StdRng::from_seed(&[0]));
+        return Ok(());
+    }
 
-        self.biases = vec![];
-        for l in 0..structure.len() {
-            self.biases.push(vec![]);
-            for _n in 0..structure[l] {
-                self.biases[l].push(0.0);
+    /// Fit the input and output pipelines to the training set, replacing the current ones.
+    /// Fails if the preprocessed values do not fit the input and output layers.
+    pub fn fit_pipelines(&mut self, training_set : &[TrainingData], input_steps : &[PreprocessingStep], output_steps : &[PreprocessingStep]) -> Result<(), NetworkError> {
+        let input_pipeline = Pipeline::fit(input_steps, &training_set.iter().map(|ts| ts.input()).collect::<Vec<_>>())?;
+        let output_pipeline = Pipeline::fit(output_steps, &training_set.iter().map(|ts| ts.output()).collect::<Vec<_>>())?;
+
+        let input_size = self.nodes[0].len();
+        if let Some(raw_size) = input_pipeline.input_size() {
+            if input_pipeline.output_size(raw_size) != input_size {
+                return Err(NetworkError::InputSizeMismatch { expected: input_size, actual: input_pipeline.output_size(raw_size) });
+            }
+        }
+        let output_size = self.nodes[self.nodes.len()-1].len();
+        if let Some(raw_size) = output_pipeline.input_size() {
+            if output_pipeline.output_size(raw_size) != output_size {
+                return Err(NetworkError::OutputSizeMismatch { expected: output_size, actual: output_pipeline.output_size(raw_size) });
             }
         }
+
+        self.input_pipeline = input_pipeline;
+        self.output_pipeline = output_pipeline;
+        return Ok(());
+    }
+
+    /// Turn raw inputs into values of the input layer
+    fn preprocess_input<'a>(&self, input : &'a [f64]) -> Result<Cow<'a, [f64]>, NetworkError> {
+        match self.input_pipeline.input_size() {
+            None => return Ok(Cow::Borrowed(input)),
+            Some(size) if size != input.len() => return Err(NetworkError::InputSizeMismatch { expected: size, actual: input.len() }),
+            Some(_) => return Ok(Cow::Owned(self.input_pipeline.transform(input)?))
+        }
+    }
+
+    /// Turn a sample with raw values into one with the values of the input and output layers
+    pub fn preprocess_sample(&self, sample : &TrainingData) -> Result<TrainingData, NetworkError> {
+        let output = match self.output_pipeline.input_size() {
+            None => sample.output().to_vec(),
+            Some(size) if size != sample.output().len() => return Err(NetworkError::OutputSizeMismatch { expected: size, actual: sample.output().len() }),
+            Some(_) => self.output_pipeline.transform(sample.output())?
+        };
+        return Ok(TrainingData::new(self.preprocess_input(sample.input())?.into_owned(), output));
+    }
+
+    /// Preprocess every sample of a training set. Borrows the set if the network has no pipelines.
+    pub fn preprocess_training_set<'a>(&self, training_set : &'a [TrainingData]) -> Result<Cow<'a, [TrainingData]>, NetworkError> {
+        if self.input_pipeline.is_empty() && self.output_pipeline.is_empty() {
+            return Ok(Cow::Borrowed(training_set));
+        }
+        return Ok(Cow::Owned(training_set.iter().map(|ts| self.preprocess_sample(ts)).collect::<Result<Vec<_>, _>>()?));
     }
 
+    /// Calculate the outputs for the given inputs without changing the network, so it can be shared between threads.
+    /// The inputs are raw values for the input pipeline and the outputs are turned back into raw values by the output pipeline.
+    pub fn predict(&self, input : &[f64]) -> Result<Vec<f64>, NetworkError> {
+        let output = self.predict_preprocessed(&self.preprocess_input(input)?)?;
+        if self.output_pipeline.is_empty() {
+            return Ok(output);
+        }
+        return Ok(self.output_pipeline.inverse_transform(&output));
+    }
+
+    /// Calculate the values of the output layer for values of the input layer, without the pipelines
+    pub fn predict_preprocessed(&self, input : &[f64]) -> Result<Vec<f64>, NetworkError> {
+        self.check_input(input)?;
+
+        let mut values = input.to_vec();
+        for l in 1..self.nodes.len() {
+            let mut sums = self.weights[l-1].left_multiply_vector(&values);
+            math::axpy(1.0, &self.biases[l], &mut sums);
+            values = vec![0.0; sums.len()];
+            self.activations[l-1].activate_layer(&sums, &mut values);
+        }
+        return Ok(values);
+    }
+
+    /// Calculate all node values from the inputs set by set_inputs and store them in the network.
+    /// Only needed to inspect the values of every layer, like the viewer does. Use predict to just get the outputs.
     pub fn calculate_network(&mut self) {
         for i in 1..self.nodes.len() {
             self.calculate_layer_values(i);
         }
     }
 
+    /// Calculate the weighted sums of the layer at layer_index from the previous layer, then apply the activation function
     fn calculate_layer_values(&mut self, layer_index : usize) {
-        for i in 0..self.nodes[layer_index].len() {
-            self.calculate_node_value(layer_index, i);
-        }
-    }
+        let sums = &mut self.weighted_sums[layer_index];
+        self.weights[layer_index-1].left_multiply_vector_into(&self.nodes[layer_index-1], sums);
+        math::axpy(1.0, &self.biases[layer_index], sums);
 
-    /// Calculate the value of the node at node_index in the layer layer_index
-    fn calculate_node_value(&mut self, layer_index : usize, node_index : usize) {
-        let mut sum = 0.0;
-        for i in 0..self.nodes[layer_index-1].len() {
-            sum += self.nodes[layer_index-1][i] * self.weights[layer_index-1][i][node_index];
-        }
-        
-        let value = NeuralNetwork::sigmoid(sum + self.biases[layer_index][node_index]);
-        self.nodes[layer_index][node_index] = value;
+        self.activations[layer_index-1].activate_layer(&self.weighted_sums[layer_index], &mut self.nodes[layer_index]);
     }
 
     pub fn sigmoid(v : f64) -> f64 {
         return 1.0 / (1.0 + std::f64::consts::E.powf(-v));
     }
 
+    /// Derivative of the sigmoid function, given the already activated value sigmoid(v)
+    pub fn sigmoid_derivative(activated : f64) -> f64 {
+        return activated * (1.0 - activated);
+    }
+
     pub fn linear(v : f64) -> f64 {
         return v;
     }
 
     /// Mutate the weights of this network by a given mutation ammount in both positive and negative directions
-    pub fn mutate_weights(&mut self, mutation : f64) {
-        let mut rng = rand::thread_rng();
-
+    pub fn mutate_weights<R: Rng>(&mut self, mutation : f64, rng : &mut R) {
+        if mutation <= 0.0 {
+            return;
+        }
         for l in 0..self.weights.len() {
-            for n in 0..self.weights[l].len() {
-                for t in 0..self.weights[l][n].len() {
-                    self.weights[l][n][t] += rng.gen_range(-mutation, mutation);
-                }
+            for weight in self.weights[l].data_mut() {
+                *weight += rng.gen_range(-mutation, mutation);
             }
         }
     }
 
     /// Mutate the biases of this network by a given mutation ammount in both positive and negative directions
-    pub fn mutate_biases(&mut self, mutation : f64) {
-        let mut rng = rand::thread_rng();
-
-        for l in 0..self.weights.len() {
-            for n in 0..self.weights[l].len() {
-                self.biases[l][n] += rng.gen_range(-mutation, mutation);
+    pub fn mutate_biases<R: Rng>(&mut self, mutation : f64, rng : &mut R) {
+        if mutation <= 0.0 {
+            return;
+        }
+        // The input layer has no weighted sum, so its biases are never used
+        for l in 1..self.biases.len() {
+            for bias in self.biases[l].iter_mut() {
+                *bias += rng.gen_range(-mutation, mutation);
             }
         }
     }
@@ -141,12 +330,46 @@ impl NeuralNetwork {
         return structure;
     }
 
-    pub fn get_structure_from_weights(&mut self, weights : Vec<Vec<Vec<f64>>>) -> Vec<usize> {
+    pub fn get_structure_from_weights(&mut self, weights : Vec<Matrix>) -> Result<Vec<usize>, NetworkError> {
+        if weights.is_empty() {
+            return Err(NetworkError::EmptyStructure);
+        }
         let mut structure = vec![];
         for l in 0..weights.len() {
-            structure.push(weights[l].len());
+            structure.push(weights[l].rows());
+            if l > 0 && weights[l-1].columns() != weights[l].rows() {
+                return Err(NetworkError::InconsistentWeights { layer: l, expected: (weights[l-1].columns(), weights[l].columns()), actual: (weights[l].rows(), weights[l].columns()) });
+            }
         }
-        structure.push(weights[weights.len()-1][0].len());
-        return structure;
+        structure.push(weights[weights.len()-1].columns());
+        return Ok(structure);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_activation_checks_the_layer_index() {
+        let mut network = NeuralNetwork::new(vec![2, 3, 1]).unwrap();
+        assert_eq!(network.set_activation(0, Activation::Relu), Err(NetworkError::LayerIndexOutOfRange { index: 0, layers: 3 }));
+        assert_eq!(network.set_activation(3, Activation::Relu), Err(NetworkError::LayerIndexOutOfRange { index: 3, layers: 3 }));
+        network.set_activation(2, Activation::Softmax).unwrap();
+        assert_eq!(network.activations, vec![Activation::default(), Activation::Softmax]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn new_networks_start_with_seeded_xavier_weights() {
+        let network = NeuralNetwork::new(vec![4, 3, 2]).unwrap();
+        let seeded = NeuralNetwork::with_initializer(vec![4, 3, 2], vec![Activation::Sigmoid; 2], Initializer::XavierUniform, 0).unwrap();
+        assert_eq!(network.weights, seeded.weights);
+        assert_eq!(network.biases, seeded.biases);
+        assert_eq!(network.weights, NeuralNetwork::new(vec![4, 3, 2]).unwrap().weights);
+
+        // Different weights in a layer, all inside the Xavier limit
+        let limit = (6.0f64 / 7.0).sqrt();
+        let first = network.weights[0].data();
+        assert!(first.iter().any(|w| *w != first[0]));
+        assert!(first.iter().all(|w| w.abs() <= limit));
+    }
+}
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+
+/// Activation function applied to the weighted sums of a layer
+#[derive(Clone, Copy, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub enum Activation {
+    #[default]
+    Sigmoid,
+    Tanh,
+    Relu,
+    /// Relu with the given slope for negative inputs
+    LeakyRelu(f64),
+    /// Exponential linear unit with the given alpha
+    Elu(f64),
+    Gelu,
+    Softplus,
+    Linear,
+    /// Normalizes the whole layer into a probability distribution. Can not be applied to single values.
+    Softmax
+}
+
+impl Activation {
+    /// Apply the activation function to a single weighted sum.
+    /// Softmax depends on the whole layer, so activate_layer handles it and never calls this for it.
+    fn activate(&self, v : f64) -> f64 {
+        match *self {
+            Activation::Sigmoid => super::NeuralNetwork::sigmoid(v),
+            Activation::Tanh => v.tanh(),
+            Activation::Relu => if v > 0.0 { v } else { 0.0 },
+            Activation::LeakyRelu(slope) => if v > 0.0 { v } else { slope * v },
+            Activation::Elu(alpha) => if v > 0.0 { v } else { alpha * (v.exp() - 1.0) },
+            Activation::Gelu => 0.5 * v * (1.0 + Activation::gelu_inner(v).tanh()),
+            Activation::Softplus => v.max(0.0) + (-v.abs()).exp().ln_1p(),
+            Activation::Linear => super::NeuralNetwork::linear(v),
+            Activation::Softmax => unreachable!("Softmax is applied to the whole layer by activate_layer")
+        }
+    }
+
+    /// Apply the activation function to all weighted sums of a layer and write the results into values
+    pub fn activate_layer(&self, sums : &[f64], values : &mut [f64]) {
+        if *self == Activation::Softmax {
+            // Subtract the largest sum to keep the exponentials from overflowing
+            let max = sums.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            let mut total = 0.0;
+            for i in 0..sums.len() {
+                values[i] = (sums[i] - max).exp();
+                total += values[i];
+            }
+            for value in values.iter_mut() {
+                *value /= total;
+            }
+            return;
+        }
+
+        for i in 0..sums.len() {
+            values[i] = self.activate(sums[i]);
+        }
+    }
+
+    /// Derivative of the activation function, given the weighted sum and the already activated value.
+    /// For softmax this is only the diagonal of its jacobian, backpropagate_layer uses the full jacobian.
+    pub fn derivative(&self, sum : f64, activated : f64) -> f64 {
+        match *self {
+            Activation::Sigmoid => super::NeuralNetwork::sigmoid_derivative(activated),
+            Activation::Tanh => 1.0 - activated * activated,
+            Activation::Relu => if sum > 0.0 { 1.0 } else { 0.0 },
+            Activation::LeakyRelu(slope) => if sum > 0.0 { 1.0 } else { slope },
+            Activation::Elu(alpha) => if sum > 0.0 { 1.0 } else { alpha * sum.exp() },
+            Activation::Gelu => {
+                let inner = Activation::gelu_inner(sum).tanh();
+                let inner_derivative = (2.0 / std::f64::consts::PI).sqrt() * (1.0 + 3.0 * 0.044715 * sum * sum);
+                0.5 * (1.0 + inner) + 0.5 * sum * (1.0 - inner * inner) * inner_derivative
+            },
+            Activation::Softplus => super::NeuralNetwork::sigmoid(sum),
+            Activation::Linear => 1.0,
+            Activation::Softmax => activated * (1.0 - activated)
+        }
+    }
+
+    /// Turn the errors with respect to the activated values of a layer into errors with respect to its weighted sums
+    pub fn backpropagate_layer(&self, sums : &[f64], values : &[f64], errors : &mut [f64]) {
+        if *self == Activation::Softmax {
+            // Multiply with the full jacobian: d_i = y_i * (e_i - sum_j(e_j * y_j))
+            let mut weighted = 0.0;
+            for j in 0..values.len() {
+                weighted += errors[j] * values[j];
+            }
+            for i in 0..values.len() {
+                errors[i] = values[i] * (errors[i] - weighted);
+            }
+            return;
+        }
+
+        for i in 0..errors.len() {
+            errors[i] *= self.derivative(sums[i], values[i]);
+        }
+    }
+
+    fn gelu_inner(v : f64) -> f64 {
+        return (2.0 / std::f64::consts::PI).sqrt() * (v + 0.044715 * v * v * v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_activation_works_on_a_layer() {
+        let sums = [-2.0, 0.0, 3.0];
+        let activations = [Activation::Sigmoid, Activation::Tanh, Activation::Relu, Activation::LeakyRelu(0.1), Activation::Elu(1.0), Activation::Gelu, Activation::Softplus, Activation::Linear, Activation::Softmax];
+        for activation in activations {
+            let mut values = [0.0; 3];
+            activation.activate_layer(&sums, &mut values);
+            assert!(values.iter().all(|v| v.is_finite()), "{:?}", activation);
+        }
+
+        let mut values = [0.0; 3];
+        Activation::Softmax.activate_layer(&sums, &mut values);
+        assert!((values.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!(values[0] < values[1] && values[1] < values[2]);
+    }
+}
@@ -0,0 +1,142 @@
+use super::{Matrix, NetworkError, NeuralNetwork};
+
+/// Buffers for running many inputs through a network at once.
+/// Keep one around to run several batches without allocating new matrices for every batch.
+#[derive(Clone, Debug, Default)]
+pub struct BatchWorkspace {
+    /// Weighted sums of every layer, one row per input
+    pub weighted_sums : Vec<Matrix>,
+    /// Node values of every layer, one row per input. The first matrix holds the inputs.
+    pub nodes : Vec<Matrix>
+}
+
+impl BatchWorkspace {
+    pub fn new() -> BatchWorkspace {
+        return BatchWorkspace::default();
+    }
+
+    /// Make sure there is a matrix of the right size for every layer, reusing the existing ones where possible
+    fn prepare(&mut self, structure : &[usize], batch_size : usize) {
+        self.weighted_sums.truncate(structure.len());
+        self.nodes.truncate(structure.len());
+        for l in 0..structure.len() {
+            if l == self.nodes.len() {
+                self.weighted_sums.push(Matrix::new(batch_size, structure[l]));
+                self.nodes.push(Matrix::new(batch_size, structure[l]));
+            }
+            else if self.nodes[l].rows() != batch_size || self.nodes[l].columns() != structure[l] {
+                self.weighted_sums[l] = Matrix::new(batch_size, structure[l]);
+                self.nodes[l] = Matrix::new(batch_size, structure[l]);
+            }
+        }
+    }
+
+    /// Output values of the last batch, one row per input
+    pub fn outputs(&self) -> &Matrix {
+        return &self.nodes[self.nodes.len()-1];
+    }
+}
+
+impl NeuralNetwork {
+    /// Calculate the outputs for many raw inputs at once, one output row for every input row.
+    /// Like predict, the inputs go through the input pipeline and the outputs are turned back into raw values by the output pipeline.
+    /// The node values stored in the network are not changed.
+    pub fn predict_matrix(&self, inputs : &Matrix) -> Result<Matrix, NetworkError> {
+        if self.input_pipeline.is_empty() && self.output_pipeline.is_empty() {
+            let mut workspace = BatchWorkspace::new();
+            self.forward_batch(inputs, &mut workspace)?;
+            return Ok(workspace.nodes.pop().unwrap());
+        }
+        return Ok(Matrix::from_rows(self.predict_batch(&inputs.to_rows())?));
+    }
+
+    /// Calculate the outputs for a list of raw inputs, using the pipelines like predict
+    pub fn predict_batch(&self, inputs : &[Vec<f64>]) -> Result<Vec<Vec<f64>>, NetworkError> {
+        return self.predict_batch_with_workspace(inputs, &mut BatchWorkspace::new());
+    }
+
+    /// Like predict_batch, but keeps the values of every layer in the given workspace.
+    /// Reusing the workspace for repeated predictions avoids allocating its matrices for every batch.
+    pub fn predict_batch_with_workspace(&self, inputs : &[Vec<f64>], workspace : &mut BatchWorkspace) -> Result<Vec<Vec<f64>>, NetworkError> {
+        if inputs.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut rows = vec![];
+        for input in inputs {
+            let input = self.preprocess_input(input)?.into_owned();
+            self.check_input(&input)?;
+            rows.push(input);
+        }
+        let outputs = self.forward_batch(&Matrix::from_rows(rows), workspace)?.to_rows();
+        if self.output_pipeline.is_empty() {
+            return Ok(outputs);
+        }
+        return Ok(outputs.iter().map(|output| self.output_pipeline.inverse_transform(output)).collect());
+    }
+
+    /// Run a batch of inputs through the network, one input per row, keeping the values of every layer in the workspace.
+    /// Returns the outputs, one row per input. Works on values of the input and output layers, without the pipelines.
+    pub fn forward_batch<'a>(&self, inputs : &Matrix, workspace : &'a mut BatchWorkspace) -> Result<&'a Matrix, NetworkError> {
+        if inputs.columns() != self.nodes[0].len() {
+            return Err(NetworkError::InputSizeMismatch { expected: self.nodes[0].len(), actual: inputs.columns() });
+        }
+        if inputs.data().iter().any(|v| !v.is_finite()) {
+            return Err(NetworkError::NonFiniteValue("input"));
+        }
+
+        workspace.prepare(&self.get_structure(), inputs.rows());
+        workspace.nodes[0].data_mut().copy_from_slice(inputs.data());
+
+        for l in 1..self.nodes.len() {
+            let (previous, current) = workspace.nodes.split_at_mut(l);
+            let sums = &mut workspace.weighted_sums[l];
+            previous[l-1].multiply_into(&self.weights[l-1], sums);
+
+            for r in 0..inputs.rows() {
+                let row = sums.row_mut(r);
+                super::math::axpy(1.0, &self.biases[l], row);
+                self.activations[l-1].activate_layer(row, current[0].row_mut(r));
+            }
+        }
+
+        return Ok(workspace.outputs());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{Activation, Initializer, PreprocessingStep, Preprocessor};
+    use crate::trainer::TrainingData;
+
+    #[test]
+    fn batch_predictions_use_the_pipelines() {
+        let mut network = NeuralNetwork::with_initializer(vec![2, 3, 1], vec![Activation::Tanh, Activation::Sigmoid], Initializer::XavierUniform, 4).unwrap();
+        let training_set : Vec<TrainingData> = (0..10).map(|i| TrainingData::new(vec![i as f64 * 100.0, 5.0 - i as f64], vec![1000.0 + i as f64 * 600.0])).collect();
+        network.fit_pipelines(&training_set, &[PreprocessingStep::all(Preprocessor::ZScore)], &[PreprocessingStep::all(Preprocessor::MinMax(0.0, 1.0))]).unwrap();
+
+        let inputs : Vec<Vec<f64>> = training_set.iter().map(|ts| ts.input().to_vec()).collect();
+        let batch = network.predict_batch(&inputs).unwrap();
+        let matrix = network.predict_matrix(&Matrix::from_rows(inputs.clone())).unwrap();
+        for i in 0..inputs.len() {
+            let single = network.predict(&inputs[i]).unwrap();
+            assert_eq!(batch[i], single);
+            assert_eq!(matrix.row(i), &single[..]);
+            assert!(single[0] >= 1000.0 && single[0] <= 6400.0);
+        }
+        assert!(matches!(network.predict_batch(&[vec![1.0]]), Err(NetworkError::InputSizeMismatch { expected: 2, actual: 1 })));
+    }
+
+    #[test]
+    fn workspace_is_reused_between_batches() {
+        let network = NeuralNetwork::with_initializer(vec![2, 4, 3], vec![Activation::Relu, Activation::Softmax], Initializer::HeUniform, 6).unwrap();
+        let inputs : Vec<Vec<f64>> = (0..5).map(|i| vec![i as f64, 1.0 - i as f64]).collect();
+
+        let mut workspace = BatchWorkspace::new();
+        for size in [5, 2, 5] {
+            let outputs = network.predict_batch_with_workspace(&inputs[..size], &mut workspace).unwrap();
+            assert_eq!(outputs, network.predict_batch(&inputs[..size]).unwrap());
+            assert_eq!(workspace.outputs().rows(), size);
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::fmt;
+
+/// Error when a network is built or used with data that does not fit it
+#[derive(Clone, Debug, PartialEq)]
+pub enum NetworkError {
+    /// The number of input values does not match the size of the input layer
+    InputSizeMismatch { expected : usize, actual : usize },
+    /// The number of expected output values does not match the size of the output layer
+    OutputSizeMismatch { expected : usize, actual : usize },
+    /// The weight matrix between layer and layer + 1 does not connect the two layers, sizes are (rows, columns)
+    InconsistentWeights { layer : usize, expected : (usize, usize), actual : (usize, usize) },
+    /// The number of biases does not match the size of the layer
+    InconsistentBiases { layer : usize, expected : usize, actual : usize },
+    /// The number of weight matrices, bias vectors or activation functions does not match the number of layers
+    LayerCountMismatch { expected : usize, actual : usize },
+    /// The layer index is the input layer, which has no activation function, or past the output layer
+    LayerIndexOutOfRange { index : usize, layers : usize },
+    /// The structure has fewer than two layers or a layer without nodes
+    EmptyStructure,
+    /// A value is NaN or infinite. Contains what kind of value it was.
+    NonFiniteValue(&'static str),
+    /// A preprocessing pipeline could not be fitted or applied
+    Preprocessing(String),
+    /// The data has fewer samples than the operation needs
+    NotEnoughSamples { needed : usize, actual : usize }
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetworkError::InputSizeMismatch { expected, actual } => write!(f, "Input vector does not match the length of the input layer: {} -> {}", actual, expected),
+            NetworkError::OutputSizeMismatch { expected, actual } => write!(f, "Output vector does not match the length of the output layer: {} -> {}", actual, expected),
+            NetworkError::InconsistentWeights { layer, expected, actual } => write!(f, "Weights of layer {} are {}x{}, expected {}x{}", layer, actual.0, actual.1, expected.0, expected.1),
+            NetworkError::InconsistentBiases { layer, expected, actual } => write!(f, "Layer {} has {} biases, expected {}", layer, actual, expected),
+            NetworkError::LayerCountMismatch { expected, actual } => write!(f, "Got values for {} layers, expected {}", actual, expected),
+            NetworkError::LayerIndexOutOfRange { index, layers } => write!(f, "Layer {} has no activation function, the network has {} layers and the input layer has none", index, layers),
+            NetworkError::EmptyStructure => write!(f, "A network needs at least two layers and every layer needs at least one node"),
+            NetworkError::NonFiniteValue(what) => write!(f, "Non-finite {} value", what),
+            NetworkError::Preprocessing(msg) => write!(f, "Preprocessing failed: {}", msg),
+            NetworkError::NotEnoughSamples { needed, actual } => write!(f, "Needs at least {} samples, got {}", needed, actual)
+        }
+    }
+}
+
+impl std::error::Error for NetworkError {}
@@ -0,0 +1,108 @@
+use rand::Rng;
+use rand::distributions::{IndependentSample, Normal};
+
+use super::Matrix;
+
+/// Strategy for choosing the starting weights of a network.
+/// fan_in and fan_out are the sizes of the two layers a weight matrix connects.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Initializer {
+    /// Uniformly distributed between a minimum and a maximum
+    Uniform(f64, f64),
+    /// Normally distributed with a mean and a standard deviation
+    Normal(f64, f64),
+    /// Glorot uniform, limit sqrt(6 / (fan_in + fan_out))
+    XavierUniform,
+    /// Glorot normal, standard deviation sqrt(2 / (fan_in + fan_out))
+    XavierNormal,
+    /// Kaiming uniform, limit sqrt(6 / fan_in). Suited for relu layers.
+    HeUniform,
+    /// Kaiming normal, standard deviation sqrt(2 / fan_in). Suited for relu layers.
+    HeNormal,
+    /// Limit sqrt(3 / fan_in)
+    LecunUniform,
+    /// Standard deviation sqrt(1 / fan_in)
+    LecunNormal,
+    /// Orthogonal rows or columns, scaled by a gain
+    Orthogonal(f64),
+    /// Every weight set to the same value
+    Constant(f64),
+    Zeros
+}
+
+impl Initializer {
+    /// Create the weights connecting a layer of fan_in nodes to a layer of fan_out nodes, with one row per node of the first layer
+    pub fn initialize_weights<R: Rng>(&self, fan_in : usize, fan_out : usize, rng : &mut R) -> Matrix {
+        let fan_sum = (fan_in + fan_out) as f64;
+        let fan_in_f = fan_in.max(1) as f64;
+
+        match *self {
+            Initializer::Uniform(min, max) => Initializer::uniform_matrix(fan_in, fan_out, min, max, rng),
+            Initializer::Normal(mean, std_dev) => Initializer::normal_matrix(fan_in, fan_out, mean, std_dev, rng),
+            Initializer::XavierUniform => {
+                let limit = (6.0 / fan_sum).sqrt();
+                Initializer::uniform_matrix(fan_in, fan_out, -limit, limit, rng)
+            },
+            Initializer::XavierNormal => Initializer::normal_matrix(fan_in, fan_out, 0.0, (2.0 / fan_sum).sqrt(), rng),
+            Initializer::HeUniform => {
+                let limit = (6.0 / fan_in_f).sqrt();
+                Initializer::uniform_matrix(fan_in, fan_out, -limit, limit, rng)
+            },
+            Initializer::HeNormal => Initializer::normal_matrix(fan_in, fan_out, 0.0, (2.0 / fan_in_f).sqrt(), rng),
+            Initializer::LecunUniform => {
+                let limit = (3.0 / fan_in_f).sqrt();
+                Initializer::uniform_matrix(fan_in, fan_out, -limit, limit, rng)
+            },
+            Initializer::LecunNormal => Initializer::normal_matrix(fan_in, fan_out, 0.0, (1.0 / fan_in_f).sqrt(), rng),
+            Initializer::Orthogonal(gain) => Initializer::orthogonal_matrix(fan_in, fan_out, gain, rng),
+            Initializer::Constant(value) => Matrix::filled(fan_in, fan_out, value),
+            Initializer::Zeros => Matrix::new(fan_in, fan_out)
+        }
+    }
+
+    fn uniform_matrix<R: Rng>(rows : usize, columns : usize, min : f64, max : f64, rng : &mut R) -> Matrix {
+        let mut matrix = Matrix::filled(rows, columns, min);
+        if max <= min {
+            return matrix;
+        }
+        for value in matrix.data_mut() {
+            *value = rng.gen_range(min, max);
+        }
+        return matrix;
+    }
+
+    fn normal_matrix<R: Rng>(rows : usize, columns : usize, mean : f64, std_dev : f64, rng : &mut R) -> Matrix {
+        let normal = Normal::new(mean, std_dev);
+        let mut matrix = Matrix::filled(rows, columns, mean);
+        for value in matrix.data_mut() {
+            *value = normal.ind_sample(rng);
+        }
+        return matrix;
+    }
+
+    /// Orthonormalize random normal vectors along the longer side of the matrix using Gram-Schmidt
+    fn orthogonal_matrix<R: Rng>(rows : usize, columns : usize, gain : f64, rng : &mut R) -> Matrix {
+        // Vectors are the rows if there are fewer rows than columns, otherwise the columns
+        let mut vectors = Initializer::normal_matrix(rows.min(columns), rows.max(columns), 0.0, 1.0, rng);
+
+        for v in 0..vectors.rows() {
+            for p in 0..v {
+                let projection = super::math::dot(vectors.row(v), vectors.row(p));
+                let previous = vectors.row(p).to_vec();
+                super::math::axpy(-projection, &previous, vectors.row_mut(v));
+            }
+            let norm = super::math::dot(vectors.row(v), vectors.row(v)).sqrt();
+            if norm > 0.0 {
+                for value in vectors.row_mut(v) {
+                    *value /= norm;
+                }
+            }
+        }
+
+        vectors.scale(gain);
+        if rows <= columns {
+            return vectors;
+        }
+        return vectors.transpose();
+    }
+}
@@ -0,0 +1,262 @@
+use std::convert::TryFrom;
+use std::ops::{Index, IndexMut};
+
+use serde::{Deserialize, Serialize};
+
+/// A vector of values, like the node values of a layer
+pub type Vector = Vec<f64>;
+
+/// Matrix with its values stored contiguously in row-major order
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "MatrixData")]
+pub struct Matrix {
+    rows : usize,
+    columns : usize,
+    data : Vec<f64>
+}
+
+/// Serialized form of a matrix, which is checked before it becomes one
+#[derive(Deserialize)]
+struct MatrixData {
+    rows : usize,
+    columns : usize,
+    data : Vec<f64>
+}
+
+impl TryFrom<MatrixData> for Matrix {
+    type Error = String;
+
+    fn try_from(matrix : MatrixData) -> Result<Matrix, String> {
+        if matrix.rows.checked_mul(matrix.columns) != Some(matrix.data.len()) {
+            return Err(format!("matrix of {}x{} values has {} values", matrix.rows, matrix.columns, matrix.data.len()));
+        }
+        return Ok(Matrix { rows: matrix.rows, columns: matrix.columns, data: matrix.data });
+    }
+}
+
+impl Matrix {
+    /// Create a matrix of the given size filled with zeros
+    pub fn new(rows : usize, columns : usize) -> Matrix {
+        return Matrix::filled(rows, columns, 0.0);
+    }
+
+    /// Create a matrix of the given size with every entry set to value
+    pub fn filled(rows : usize, columns : usize, value : f64) -> Matrix {
+        return Matrix {
+            rows,
+            columns,
+            data: vec![value; rows * columns]
+        };
+    }
+
+    /// Create a matrix from row-major data. The data must contain rows * columns values.
+    pub fn from_vec(rows : usize, columns : usize, data : Vec<f64>) -> Matrix {
+        assert_eq!(data.len(), rows * columns, "Matrix data does not match its size");
+        return Matrix { rows, columns, data };
+    }
+
+    /// Create a matrix from a list of rows. All rows must have the same length.
+    pub fn from_rows(rows : Vec<Vec<f64>>) -> Matrix {
+        let columns = if rows.is_empty() { 0 } else { rows[0].len() };
+        let mut data = Vec::with_capacity(rows.len() * columns);
+        for r in 0..rows.len() {
+            assert_eq!(rows[r].len(), columns, "All rows of a matrix must have the same length");
+            data.extend_from_slice(&rows[r]);
+        }
+        return Matrix { rows: rows.len(), columns, data };
+    }
+
+    /// Copy the matrix into a list of rows
+    pub fn to_rows(&self) -> Vec<Vec<f64>> {
+        return (0..self.rows).map(|r| self.row(r).to_vec()).collect();
+    }
+
+    pub fn rows(&self) -> usize {
+        return self.rows;
+    }
+
+    pub fn columns(&self) -> usize {
+        return self.columns;
+    }
+
+    /// All values in row-major order
+    pub fn data(&self) -> &[f64] {
+        return &self.data;
+    }
+
+    /// All values in row-major order
+    pub fn data_mut(&mut self) -> &mut [f64] {
+        return &mut self.data;
+    }
+
+    pub fn row(&self, row : usize) -> &[f64] {
+        return &self.data[row * self.columns..(row + 1) * self.columns];
+    }
+
+    pub fn row_mut(&mut self, row : usize) -> &mut [f64] {
+        return &mut self.data[row * self.columns..(row + 1) * self.columns];
+    }
+
+    pub fn transpose(&self) -> Matrix {
+        let mut result = Matrix::new(self.columns, self.rows);
+        for r in 0..self.rows {
+            for c in 0..self.columns {
+                result.data[c * self.rows + r] = self.data[r * self.columns + c];
+            }
+        }
+        return result;
+    }
+
+    /// Matrix product self * other
+    pub fn multiply(&self, other : &Matrix) -> Matrix {
+        let mut result = Matrix::new(self.rows, other.columns);
+        self.multiply_into(other, &mut result);
+        return result;
+    }
+
+    /// Matrix product self * other, written into result which must already have the right size
+    pub fn multiply_into(&self, other : &Matrix, result : &mut Matrix) {
+        assert_eq!(self.columns, other.rows, "Matrix sizes do not match for multiplication");
+        assert!(result.rows == self.rows && result.columns == other.columns, "Result matrix has the wrong size");
+        for value in result.data.iter_mut() {
+            *value = 0.0;
+        }
+        // Loop order i, k, j walks both other and result row by row
+        for i in 0..self.rows {
+            let result_row = &mut result.data[i * other.columns..(i + 1) * other.columns];
+            for k in 0..self.columns {
+                axpy(self.data[i * self.columns + k], other.row(k), result_row);
+            }
+        }
+    }
+
+    /// Matrix-vector product self * v, one value per row
+    pub fn multiply_vector(&self, v : &[f64]) -> Vector {
+        assert_eq!(self.columns, v.len(), "Vector length does not match the matrix columns");
+        return (0..self.rows).map(|r| dot(self.row(r), v)).collect();
+    }
+
+    /// Vector-matrix product v * self, one value per column
+    pub fn left_multiply_vector(&self, v : &[f64]) -> Vector {
+        let mut result = vec![0.0; self.columns];
+        self.left_multiply_vector_into(v, &mut result);
+        return result;
+    }
+
+    /// Vector-matrix product v * self, written into result
+    pub fn left_multiply_vector_into(&self, v : &[f64], result : &mut [f64]) {
+        assert_eq!(self.rows, v.len(), "Vector length does not match the matrix rows");
+        assert_eq!(self.columns, result.len(), "Result length does not match the matrix columns");
+        for value in result.iter_mut() {
+            *value = 0.0;
+        }
+        for r in 0..self.rows {
+            axpy(v[r], self.row(r), result);
+        }
+    }
+
+    /// Outer product a * b^T with a.len() rows and b.len() columns
+    pub fn outer(a : &[f64], b : &[f64]) -> Matrix {
+        let mut result = Matrix::new(a.len(), b.len());
+        result.add_outer(1.0, a, b);
+        return result;
+    }
+
+    /// Add scale * a * b^T to this matrix
+    pub fn add_outer(&mut self, scale : f64, a : &[f64], b : &[f64]) {
+        assert!(a.len() == self.rows && b.len() == self.columns, "Vector lengths do not match the matrix size");
+        for r in 0..self.rows {
+            let row = &mut self.data[r * self.columns..(r + 1) * self.columns];
+            axpy(scale * a[r], b, row);
+        }
+    }
+
+    /// Apply a function to every entry
+    pub fn map<F: Fn(f64) -> f64>(&self, f : F) -> Matrix {
+        return Matrix {
+            rows: self.rows,
+            columns: self.columns,
+            data: self.data.iter().map(|v| f(*v)).collect()
+        };
+    }
+
+    /// Elementwise product of two matrices of the same size
+    pub fn hadamard(&self, other : &Matrix) -> Matrix {
+        self.assert_same_size(other);
+        return Matrix {
+            rows: self.rows,
+            columns: self.columns,
+            data: hadamard(&self.data, &other.data)
+        };
+    }
+
+    /// Add scale * other to this matrix
+    pub fn add_scaled(&mut self, scale : f64, other : &Matrix) {
+        self.assert_same_size(other);
+        axpy(scale, &other.data, &mut self.data);
+    }
+
+    pub fn scale(&mut self, factor : f64) {
+        for value in self.data.iter_mut() {
+            *value *= factor;
+        }
+    }
+
+    fn assert_same_size(&self, other : &Matrix) {
+        assert!(self.rows == other.rows && self.columns == other.columns, "Matrix sizes do not match");
+    }
+}
+
+impl Index<(usize, usize)> for Matrix {
+    type Output = f64;
+
+    /// Entry at (row, column)
+    fn index(&self, (row, column) : (usize, usize)) -> &f64 {
+        assert!(row < self.rows && column < self.columns, "Matrix index out of bounds");
+        return &self.data[row * self.columns + column];
+    }
+}
+
+impl IndexMut<(usize, usize)> for Matrix {
+    fn index_mut(&mut self, (row, column) : (usize, usize)) -> &mut f64 {
+        assert!(row < self.rows && column < self.columns, "Matrix index out of bounds");
+        return &mut self.data[row * self.columns + column];
+    }
+}
+
+/// Dot product of two vectors of the same length
+pub fn dot(a : &[f64], b : &[f64]) -> f64 {
+    assert_eq!(a.len(), b.len(), "Vector lengths do not match");
+    return a.iter().zip(b).map(|(x, y)| x * y).sum();
+}
+
+/// y += a * x
+pub fn axpy(a : f64, x : &[f64], y : &mut [f64]) {
+    assert_eq!(x.len(), y.len(), "Vector lengths do not match");
+    for (yi, xi) in y.iter_mut().zip(x) {
+        *yi += a * xi;
+    }
+}
+
+/// Elementwise sum of two vectors
+pub fn add(a : &[f64], b : &[f64]) -> Vector {
+    assert_eq!(a.len(), b.len(), "Vector lengths do not match");
+    return a.iter().zip(b).map(|(x, y)| x + y).collect();
+}
+
+/// Elementwise difference a - b of two vectors
+pub fn subtract(a : &[f64], b : &[f64]) -> Vector {
+    assert_eq!(a.len(), b.len(), "Vector lengths do not match");
+    return a.iter().zip(b).map(|(x, y)| x - y).collect();
+}
+
+/// Elementwise product of two vectors
+pub fn hadamard(a : &[f64], b : &[f64]) -> Vector {
+    assert_eq!(a.len(), b.len(), "Vector lengths do not match");
+    return a.iter().zip(b).map(|(x, y)| x * y).collect();
+}
+
+/// Multiply every value of a vector by a factor
+pub fn scale(v : &[f64], factor : f64) -> Vector {
+    return v.iter().map(|x| x * factor).collect();
+}
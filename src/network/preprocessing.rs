@@ -0,0 +1,338 @@
+use serde::{Deserialize, Serialize};
+
+use super::NetworkError;
+
+/// A transformation of some values of the input or output vectors, fitted to the training set
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Preprocessor {
+    /// Scale linearly, so the smallest value of the training set becomes the first and the largest the second given value
+    MinMax(f64, f64),
+    /// Subtract the mean of the training set and divide by its standard deviation
+    ZScore,
+    /// Natural logarithm of 1 + value, for values larger than -1 that span several orders of magnitude
+    Log,
+    /// Replace a value with one value per distinct value of the training set, which is 1 for the matching one and 0 for all others.
+    /// Unknown values become all zeros. Turned back into the value with the largest output.
+    OneHot,
+    /// Replace every distinct value of the training set with its index in the sorted values. Unknown values are an error.
+    LabelEncoding
+}
+
+/// A preprocessor and the values it transforms
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreprocessingStep {
+    pub preprocessor : Preprocessor,
+    /// Indices of the transformed values in the vector as it comes out of the previous step. Empty to transform all values.
+    pub columns : Vec<usize>
+}
+
+impl PreprocessingStep {
+    /// Transform all values
+    pub fn all(preprocessor : Preprocessor) -> PreprocessingStep {
+        return PreprocessingStep { preprocessor, columns: vec![] };
+    }
+
+    /// Transform the values at the given indices
+    pub fn columns(preprocessor : Preprocessor, columns : Vec<usize>) -> PreprocessingStep {
+        return PreprocessingStep { preprocessor, columns };
+    }
+}
+
+/// A step with the parameters learned from the training set
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(super) enum FittedStep {
+    /// value * scale + shift, used by min-max scaling and z-score standardization
+    Affine { columns : Vec<usize>, scale : Vec<f64>, shift : Vec<f64> },
+    Log { columns : Vec<usize> },
+    OneHot { columns : Vec<usize>, categories : Vec<Vec<f64>> },
+    LabelEncoding { columns : Vec<usize>, categories : Vec<Vec<f64>> }
+}
+
+impl FittedStep {
+    fn columns(&self) -> &[usize] {
+        match self {
+            FittedStep::Affine { columns, .. } | FittedStep::Log { columns } | FittedStep::OneHot { columns, .. } | FittedStep::LabelEncoding { columns, .. } => columns
+        }
+    }
+
+    /// Number of values this step turns a vector of the given size into
+    fn output_size(&self, input_size : usize) -> usize {
+        match self {
+            FittedStep::OneHot { categories, .. } => input_size - categories.len() + categories.iter().map(|c| c.len()).sum::<usize>(),
+            _ => input_size
+        }
+    }
+}
+
+/// Fitted preprocessing steps, applied one after the other. An empty pipeline leaves all values unchanged.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Pipeline {
+    /// Number of values the first step expects
+    input_size : usize,
+    steps : Vec<FittedStep>
+}
+
+impl Pipeline {
+    /// A pipeline that leaves all values unchanged
+    pub fn new() -> Pipeline {
+        return Pipeline::default();
+    }
+
+    /// Fit the steps to the given vectors, each step to the output of the previous one
+    pub fn fit(steps : &[PreprocessingStep], rows : &[&[f64]]) -> Result<Pipeline, NetworkError> {
+        if steps.is_empty() {
+            return Ok(Pipeline::new());
+        }
+        if rows.is_empty() {
+            return Err(NetworkError::Preprocessing("can not fit a pipeline without data".to_string()));
+        }
+
+        let input_size = rows[0].len();
+        if rows.iter().any(|row| row.len() != input_size) {
+            return Err(NetworkError::Preprocessing("all vectors of the training set need the same length".to_string()));
+        }
+        if rows.iter().any(|row| row.iter().any(|v| !v.is_finite())) {
+            return Err(NetworkError::NonFiniteValue("preprocessing"));
+        }
+        let mut pipeline = Pipeline { input_size, steps: vec![] };
+        let mut current : Vec<Vec<f64>> = rows.iter().map(|row| row.to_vec()).collect();
+        for step in steps {
+            let fitted = Pipeline::fit_step(step, &current)?;
+            for row in current.iter_mut() {
+                *row = Pipeline::apply_step(&fitted, row)?;
+            }
+            pipeline.steps.push(fitted);
+        }
+        return Ok(pipeline);
+    }
+
+    fn fit_step(step : &PreprocessingStep, rows : &[Vec<f64>]) -> Result<FittedStep, NetworkError> {
+        let size = rows[0].len();
+        let columns = if step.columns.is_empty() { (0..size).collect() } else { step.columns.clone() };
+        if let Some(column) = columns.iter().find(|c| **c >= size) {
+            return Err(NetworkError::Preprocessing(format!("column {} does not exist, the vectors have {} values", column, size)));
+        }
+        if has_duplicates(&columns) {
+            return Err(NetworkError::Preprocessing("a step can only transform every column once".to_string()));
+        }
+        let values = |c : usize| rows.iter().map(move |row| row[c]);
+
+        match step.preprocessor {
+            Preprocessor::MinMax(low, high) => {
+                if !(low.is_finite() && high.is_finite()) || low == high {
+                    return Err(NetworkError::Preprocessing(format!("can not scale into the range {} to {}", low, high)));
+                }
+                let mut scale = vec![];
+                let mut shift = vec![];
+                for c in columns.iter().copied() {
+                    let min = values(c).fold(f64::INFINITY, f64::min);
+                    let max = values(c).fold(f64::NEG_INFINITY, f64::max);
+                    // A constant value is only moved to the low end, so it can be turned back
+                    let s = if max > min { (high - low) / (max - min) } else { 1.0 };
+                    scale.push(s);
+                    shift.push(low - min * s);
+                }
+                return Ok(FittedStep::Affine { columns, scale, shift });
+            },
+            Preprocessor::ZScore => {
+                let mut scale = vec![];
+                let mut shift = vec![];
+                for c in columns.iter().copied() {
+                    let mean = values(c).sum::<f64>() / rows.len() as f64;
+                    let deviation = (values(c).map(|v| (v - mean).powi(2)).sum::<f64>() / rows.len() as f64).sqrt();
+                    let s = if deviation > 0.0 { 1.0 / deviation } else { 1.0 };
+                    scale.push(s);
+                    shift.push(-mean * s);
+                }
+                return Ok(FittedStep::Affine { columns, scale, shift });
+            },
+            Preprocessor::Log => {
+                for c in columns.iter().copied() {
+                    if values(c).any(|v| v <= -1.0) {
+                        return Err(NetworkError::Preprocessing(format!("column {} contains values of -1 or less, which have no logarithm", c)));
+                    }
+                }
+                return Ok(FittedStep::Log { columns });
+            },
+            Preprocessor::OneHot | Preprocessor::LabelEncoding => {
+                let mut categories = vec![];
+                for c in columns.iter().copied() {
+                    let mut distinct : Vec<f64> = values(c).collect();
+                    distinct.sort_by(|a, b| a.total_cmp(b));
+                    distinct.dedup();
+                    categories.push(distinct);
+                }
+                if step.preprocessor == Preprocessor::OneHot {
+                    return Ok(FittedStep::OneHot { columns, categories });
+                }
+                return Ok(FittedStep::LabelEncoding { columns, categories });
+            }
+        }
+    }
+
+    /// Number of values the pipeline expects, or None if it is empty and takes vectors of any size
+    pub fn input_size(&self) -> Option<usize> {
+        if self.steps.is_empty() {
+            return None;
+        }
+        return Some(self.input_size);
+    }
+
+    /// Number of values the pipeline turns a vector of the given size into
+    pub fn output_size(&self, input_size : usize) -> usize {
+        return self.steps.iter().fold(input_size, |size, step| step.output_size(size));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.steps.is_empty();
+    }
+
+    pub(super) fn from_steps(input_size : usize, steps : Vec<FittedStep>) -> Pipeline {
+        return Pipeline { input_size, steps };
+    }
+
+    pub(super) fn steps(&self) -> &[FittedStep] {
+        return &self.steps;
+    }
+
+    /// Apply all steps to a vector of raw values
+    pub fn transform(&self, values : &[f64]) -> Result<Vec<f64>, NetworkError> {
+        let mut current = values.to_vec();
+        for step in &self.steps {
+            current = Pipeline::apply_step(step, &current)?;
+        }
+        if current.iter().any(|v| !v.is_finite()) {
+            return Err(NetworkError::NonFiniteValue("preprocessed"));
+        }
+        return Ok(current);
+    }
+
+    /// Undo all steps, turning the values the network works with back into the original units
+    pub fn inverse_transform(&self, values : &[f64]) -> Vec<f64> {
+        let mut current = values.to_vec();
+        for step in self.steps.iter().rev() {
+            current = Pipeline::invert_step(step, &current);
+        }
+        return current;
+    }
+
+    fn apply_step(step : &FittedStep, values : &[f64]) -> Result<Vec<f64>, NetworkError> {
+        let mut result = values.to_vec();
+        match step {
+            FittedStep::Affine { columns, scale, shift } => {
+                for i in 0..columns.len() {
+                    result[columns[i]] = values[columns[i]] * scale[i] + shift[i];
+                }
+            },
+            FittedStep::Log { columns } => {
+                for c in columns {
+                    result[*c] = values[*c].ln_1p();
+                }
+            },
+            FittedStep::LabelEncoding { columns, categories } => {
+                for i in 0..columns.len() {
+                    match categories[i].iter().position(|category| *category == values[columns[i]]) {
+                        Some(index) => result[columns[i]] = index as f64,
+                        None => return Err(NetworkError::Preprocessing(format!("value {} of column {} was not in the training set", values[columns[i]], columns[i])))
+                    }
+                }
+            },
+            FittedStep::OneHot { columns, categories } => {
+                result.clear();
+                for v in 0..values.len() {
+                    match columns.iter().position(|c| *c == v) {
+                        Some(i) => result.extend(categories[i].iter().map(|category| if *category == values[v] { 1.0 } else { 0.0 })),
+                        None => result.push(values[v])
+                    }
+                }
+            }
+        }
+        return Ok(result);
+    }
+
+    fn invert_step(step : &FittedStep, values : &[f64]) -> Vec<f64> {
+        let mut result = values.to_vec();
+        match step {
+            FittedStep::Affine { columns, scale, shift } => {
+                for i in 0..columns.len() {
+                    result[columns[i]] = (values[columns[i]] - shift[i]) / scale[i];
+                }
+            },
+            FittedStep::Log { columns } => {
+                for c in columns {
+                    result[*c] = values[*c].exp_m1();
+                }
+            },
+            FittedStep::LabelEncoding { columns, categories } => {
+                for i in 0..columns.len() {
+                    let index = values[columns[i]].round().max(0.0) as usize;
+                    result[columns[i]] = categories[i][index.min(categories[i].len() - 1)];
+                }
+            },
+            FittedStep::OneHot { columns, categories } => {
+                // Each encoded column becomes the category with the largest value, like the most likely class of a classifier
+                result.clear();
+                let mut position = 0;
+                let original_size = values.len() + categories.len() - categories.iter().map(|c| c.len()).sum::<usize>();
+                for v in 0..original_size {
+                    match columns.iter().position(|c| *c == v) {
+                        Some(i) => {
+                            let encoded = &values[position..position+categories[i].len()];
+                            let mut best = 0;
+                            for j in 1..encoded.len() {
+                                if encoded[j] > encoded[best] {
+                                    best = j;
+                                }
+                            }
+                            result.push(categories[i][best]);
+                            position += categories[i].len();
+                        },
+                        None => {
+                            result.push(values[position]);
+                            position += 1;
+                        }
+                    }
+                }
+            }
+        }
+        return result;
+    }
+
+    /// Check that the steps fit together, for pipelines read from a file. Returns the output size.
+    pub(super) fn validate(&self) -> Result<usize, String> {
+        let mut size = self.input_size;
+        for step in &self.steps {
+            let columns = step.columns();
+            if columns.iter().any(|c| *c >= size) {
+                return Err(format!("preprocessing column outside of the {} values", size));
+            }
+            if has_duplicates(columns) {
+                return Err("preprocessing column used twice in one step".to_string());
+            }
+            let parameters_match = match step {
+                FittedStep::Affine { scale, shift, .. } => {
+                    if scale.iter().chain(shift).any(|v| !v.is_finite()) || scale.contains(&0.0) {
+                        return Err("invalid preprocessing scale".to_string());
+                    }
+                    scale.len() == columns.len() && shift.len() == columns.len()
+                },
+                FittedStep::Log { .. } => true,
+                FittedStep::OneHot { categories, .. } | FittedStep::LabelEncoding { categories, .. } => {
+                    categories.len() == columns.len() && categories.iter().all(|c| !c.is_empty())
+                }
+            };
+            if !parameters_match {
+                return Err("preprocessing parameters do not match the columns".to_string());
+            }
+            size = step.output_size(size);
+        }
+        return Ok(size);
+    }
+}
+
+fn has_duplicates(columns : &[usize]) -> bool {
+    let mut sorted = columns.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    return sorted.len() != columns.len();
+}
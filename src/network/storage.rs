@@ -0,0 +1,638 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Activation, Matrix, NetworkError, NeuralNetwork, Pipeline};
+use super::preprocessing::FittedStep;
+
+/// Version of the file format written by this crate. Files with a different version are rejected when loading,
+/// except for version 1 files, which are the same without preprocessing pipelines.
+pub const FORMAT_VERSION : u32 = 2;
+
+/// Oldest version that can still be loaded
+const OLDEST_VERSION : u32 = 1;
+
+/// Name stored in the format field of json files
+const JSON_FORMAT_NAME : &str = "neural-network";
+
+/// First bytes of every binary file
+const BINARY_MAGIC : &[u8; 4] = b"RNN\0";
+
+/// Format to save a network in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StorageFormat {
+    /// Human readable json
+    Json,
+    /// Compact little endian binary
+    Binary
+}
+
+/// Error when saving or loading a network
+#[derive(Debug)]
+pub enum StorageError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// The data is neither a json network file nor starts with the binary magic bytes
+    UnknownFormat,
+    /// The file was written with a format version this crate can not read
+    UnsupportedVersion(u32),
+    /// The data ended early, has trailing bytes or contains invalid values
+    Corrupt(String),
+    /// The structure, weights, biases and activations of the file do not fit together
+    ShapeMismatch(NetworkError)
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StorageError::Io(e) => write!(f, "Could not access network file: {}", e),
+            StorageError::Json(e) => write!(f, "Invalid json network file: {}", e),
+            StorageError::UnknownFormat => write!(f, "Data is not a network file"),
+            StorageError::UnsupportedVersion(v) => write!(f, "Unsupported network file version {} (expected {})", v, FORMAT_VERSION),
+            StorageError::Corrupt(msg) => write!(f, "Corrupt network file: {}", msg),
+            StorageError::ShapeMismatch(e) => write!(f, "Network file does not describe a valid network: {}", e)
+        }
+    }
+}
+
+impl std::error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StorageError::Io(e) => Some(e),
+            StorageError::Json(e) => Some(e),
+            StorageError::ShapeMismatch(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for StorageError {
+    fn from(e : io::Error) -> StorageError {
+        return StorageError::Io(e);
+    }
+}
+
+impl From<NetworkError> for StorageError {
+    fn from(e : NetworkError) -> StorageError {
+        return StorageError::ShapeMismatch(e);
+    }
+}
+
+impl From<serde_json::Error> for StorageError {
+    fn from(e : serde_json::Error) -> StorageError {
+        return StorageError::Json(e);
+    }
+}
+
+/// Everything stored about a network. The node values are not stored, they are recalculated from the inputs.
+/// Weights are stored as nested lists indexed [layer][from][to] to stay readable.
+#[derive(Serialize, Deserialize)]
+struct NetworkFile {
+    format : String,
+    version : u32,
+    structure : Vec<usize>,
+    activations : Vec<Activation>,
+    weights : Vec<Vec<Vec<f64>>>,
+    biases : Vec<Vec<f64>>,
+    #[serde(default)]
+    input_pipeline : Pipeline,
+    #[serde(default)]
+    output_pipeline : Pipeline
+}
+
+impl NetworkFile {
+    fn from_network(network : &NeuralNetwork) -> NetworkFile {
+        return NetworkFile {
+            format: JSON_FORMAT_NAME.to_string(),
+            version: FORMAT_VERSION,
+            structure: network.get_structure(),
+            activations: network.activations.clone(),
+            weights: network.weights.iter().map(|w| w.to_rows()).collect(),
+            biases: network.biases.clone(),
+            input_pipeline: network.input_pipeline.clone(),
+            output_pipeline: network.output_pipeline.clone()
+        };
+    }
+
+    /// Check that the weights and biases have the sizes of the stored structure.
+    /// Done before the network is built, so a corrupt structure can not allocate more than the file contains.
+    fn check_shapes(&self) -> Result<(), NetworkError> {
+        let structure = &self.structure;
+        if structure.len() < 2 || structure.contains(&0) {
+            return Err(NetworkError::EmptyStructure);
+        }
+        if self.weights.len() != structure.len() - 1 {
+            return Err(NetworkError::LayerCountMismatch { expected: structure.len() - 1, actual: self.weights.len() });
+        }
+        if self.biases.len() != structure.len() {
+            return Err(NetworkError::LayerCountMismatch { expected: structure.len(), actual: self.biases.len() });
+        }
+        for l in 0..structure.len() {
+            if self.biases[l].len() != structure[l] {
+                return Err(NetworkError::InconsistentBiases { layer: l, expected: structure[l], actual: self.biases[l].len() });
+            }
+        }
+        for l in 0..self.weights.len() {
+            let expected = (structure[l], structure[l+1]);
+            if self.weights[l].len() != structure[l] {
+                let columns = self.weights[l].first().map_or(0, |row| row.len());
+                return Err(NetworkError::InconsistentWeights { layer: l, expected, actual: (self.weights[l].len(), columns) });
+            }
+            if let Some(row) = self.weights[l].iter().find(|row| row.len() != structure[l+1]) {
+                return Err(NetworkError::InconsistentWeights { layer: l, expected, actual: (self.weights[l].len(), row.len()) });
+            }
+        }
+        return Ok(());
+    }
+
+    /// Check that all parts of the file fit the stored structure and build the network
+    fn into_network(self) -> Result<NeuralNetwork, StorageError> {
+        self.check_shapes()?;
+        let mut network = NeuralNetwork::with_activations(self.structure, self.activations)?;
+
+        network.set_weights(self.weights.into_iter().map(Matrix::from_rows).collect())?;
+        network.set_biases(self.biases)?;
+
+        // The pipelines must turn raw values into exactly the values of the input and output layers
+        let layers = [(&self.input_pipeline, network.nodes[0].len()), (&self.output_pipeline, network.nodes[network.nodes.len()-1].len())];
+        for (pipeline, layer_size) in layers {
+            if pipeline.is_empty() {
+                continue;
+            }
+            if pipeline.validate().map_err(StorageError::Corrupt)? != layer_size {
+                return Err(StorageError::Corrupt("preprocessing pipeline does not fit the network".to_string()));
+            }
+        }
+        network.input_pipeline = self.input_pipeline;
+        network.output_pipeline = self.output_pipeline;
+        return Ok(network);
+    }
+}
+
+impl NeuralNetwork {
+    /// Save this network to a file in the given format
+    pub fn save<P: AsRef<Path>>(&self, path : P, format : StorageFormat) -> Result<(), StorageError> {
+        let data = match format {
+            StorageFormat::Json => self.to_json()?.into_bytes(),
+            StorageFormat::Binary => self.to_binary()?
+        };
+        fs::write(path, data)?;
+        return Ok(());
+    }
+
+    /// Load a network from a file written by save. The format is detected from the content of the file.
+    pub fn load<P: AsRef<Path>>(path : P) -> Result<NeuralNetwork, StorageError> {
+        let data = fs::read(path)?;
+        if data.starts_with(BINARY_MAGIC) {
+            return NeuralNetwork::from_binary(&data);
+        }
+        if data.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{') {
+            let text = String::from_utf8(data).map_err(|_| StorageError::Corrupt("json file is not valid utf-8".to_string()))?;
+            return NeuralNetwork::from_json(&text);
+        }
+        return Err(StorageError::UnknownFormat);
+    }
+
+    /// Serialize this network as json. Fails if a parameter is NaN or infinite, as such a file could not be loaded again.
+    pub fn to_json(&self) -> Result<String, StorageError> {
+        self.check_finite_parameters()?;
+        return Ok(serde_json::to_string_pretty(&NetworkFile::from_network(self))?);
+    }
+
+    /// Check that the weights, biases and activation parameters are finite, like the loaders do
+    fn check_finite_parameters(&self) -> Result<(), NetworkError> {
+        for layer in &self.weights {
+            NeuralNetwork::check_finite(layer.data(), "weight")?;
+        }
+        for layer in &self.biases {
+            NeuralNetwork::check_finite(layer, "bias")?;
+        }
+        for activation in &self.activations {
+            if let Activation::LeakyRelu(parameter) | Activation::Elu(parameter) = *activation {
+                NeuralNetwork::check_finite(&[parameter], "activation parameter")?;
+            }
+        }
+        return Ok(());
+    }
+
+    /// Read a network from json created by to_json
+    pub fn from_json(json : &str) -> Result<NeuralNetwork, StorageError> {
+        // Check the header first, so files of other versions get a version error instead of a parse error
+        let header : serde_json::Value = serde_json::from_str(json)?;
+        if header.get("format").and_then(|f| f.as_str()) != Some(JSON_FORMAT_NAME) {
+            return Err(StorageError::UnknownFormat);
+        }
+        match header.get("version").and_then(|v| v.as_u64()) {
+            Some(v) if v >= OLDEST_VERSION as u64 && v <= FORMAT_VERSION as u64 => (),
+            Some(v) => return Err(StorageError::UnsupportedVersion(v as u32)),
+            None => return Err(StorageError::Corrupt("missing format version".to_string()))
+        }
+
+        let file : NetworkFile = serde_json::from_value(header)?;
+        return file.into_network();
+    }
+
+    /// Serialize this network into the binary format:
+    /// magic, version, layer count and layer sizes as u32, one activation per layer as u8 tag and f64 parameter,
+    /// then all weights [layer][from][to] and all biases [layer][node] as f64, followed by the input and output pipelines.
+    /// Everything is little endian. Fails if a parameter is NaN or infinite, as such a file could not be loaded again.
+    pub fn to_binary(&self) -> Result<Vec<u8>, StorageError> {
+        self.check_finite_parameters()?;
+        let mut data = BINARY_MAGIC.to_vec();
+        data.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+
+        let structure = self.get_structure();
+        data.extend_from_slice(&(structure.len() as u32).to_le_bytes());
+        for size in &structure {
+            data.extend_from_slice(&(*size as u32).to_le_bytes());
+        }
+
+        for activation in &self.activations {
+            let (tag, parameter) = match *activation {
+                Activation::Sigmoid => (0, 0.0),
+                Activation::Tanh => (1, 0.0),
+                Activation::Relu => (2, 0.0),
+                Activation::LeakyRelu(slope) => (3, slope),
+                Activation::Elu(alpha) => (4, alpha),
+                Activation::Gelu => (5, 0.0),
+                Activation::Softplus => (6, 0.0),
+                Activation::Linear => (7, 0.0),
+                Activation::Softmax => (8, 0.0)
+            };
+            data.push(tag);
+            data.extend_from_slice(&f64::to_le_bytes(parameter));
+        }
+
+        for layer in &self.weights {
+            for w in layer.data() {
+                data.extend_from_slice(&w.to_le_bytes());
+            }
+        }
+        for layer in &self.biases {
+            for b in layer {
+                data.extend_from_slice(&b.to_le_bytes());
+            }
+        }
+
+        write_pipeline(&mut data, &self.input_pipeline);
+        write_pipeline(&mut data, &self.output_pipeline);
+
+        return Ok(data);
+    }
+
+    /// Read a network from data created by to_binary
+    pub fn from_binary(data : &[u8]) -> Result<NeuralNetwork, StorageError> {
+        let mut reader = BinaryReader { data, position: 0 };
+
+        if reader.take(4)? != BINARY_MAGIC {
+            return Err(StorageError::UnknownFormat);
+        }
+        let version = reader.read_u32()?;
+        if !(OLDEST_VERSION..=FORMAT_VERSION).contains(&version) {
+            return Err(StorageError::UnsupportedVersion(version));
+        }
+
+        let layer_count = reader.read_u32()? as usize;
+        let mut structure = vec![];
+        for _l in 0..layer_count {
+            structure.push(reader.read_u32()? as usize);
+        }
+        // Reject absurd sizes before allocating anything for them
+        let parameter_count = (0..layer_count.saturating_sub(1)).fold(0usize, |count, l| count.saturating_add(structure[l].saturating_mul(structure[l+1])).saturating_add(structure[l+1]));
+        if parameter_count.saturating_mul(8) > data.len() {
+            return Err(StorageError::Corrupt(format!("structure {:?} is larger than the file", structure)));
+        }
+
+        let mut activations = vec![];
+        for _l in 1..layer_count {
+            let tag = reader.take(1)?[0];
+            let parameter = reader.read_f64()?;
+            activations.push(match tag {
+                0 => Activation::Sigmoid,
+                1 => Activation::Tanh,
+                2 => Activation::Relu,
+                3 => Activation::LeakyRelu(parameter),
+                4 => Activation::Elu(parameter),
+                5 => Activation::Gelu,
+                6 => Activation::Softplus,
+                7 => Activation::Linear,
+                8 => Activation::Softmax,
+                _ => return Err(StorageError::Corrupt(format!("unknown activation tag {}", tag)))
+            });
+        }
+
+        let mut weights = vec![];
+        for l in 0..layer_count.saturating_sub(1) {
+            let mut layer = vec![];
+            for _n in 0..structure[l] {
+                let mut node = vec![];
+                for _t in 0..structure[l+1] {
+                    node.push(reader.read_f64()?);
+                }
+                layer.push(node);
+            }
+            weights.push(layer);
+        }
+
+        let mut biases = vec![];
+        for l in 0..layer_count {
+            let mut layer = vec![];
+            for _n in 0..structure[l] {
+                layer.push(reader.read_f64()?);
+            }
+            biases.push(layer);
+        }
+
+        let (input_pipeline, output_pipeline) = if version >= 2 {
+            (read_pipeline(&mut reader)?, read_pipeline(&mut reader)?)
+        }
+        else {
+            (Pipeline::new(), Pipeline::new())
+        };
+
+        if reader.position != data.len() {
+            return Err(StorageError::Corrupt(format!("{} unexpected bytes after the network", data.len() - reader.position)));
+        }
+
+        let file = NetworkFile {
+            format: JSON_FORMAT_NAME.to_string(),
+            version,
+            structure,
+            activations,
+            weights,
+            biases,
+            input_pipeline,
+            output_pipeline
+        };
+        return file.into_network();
+    }
+}
+
+/// Append a pipeline as its input size and step count as u32, then every step as u8 tag, column count and columns as u32 and its parameters.
+/// Scales and shifts are one f64 per column, categories a u32 count and the f64 categories per column.
+fn write_pipeline(data : &mut Vec<u8>, pipeline : &Pipeline) {
+    data.extend_from_slice(&(pipeline.input_size().unwrap_or(0) as u32).to_le_bytes());
+    data.extend_from_slice(&(pipeline.steps().len() as u32).to_le_bytes());
+    for step in pipeline.steps() {
+        let (tag, columns) = match step {
+            FittedStep::Affine { columns, .. } => (0, columns),
+            FittedStep::Log { columns } => (1, columns),
+            FittedStep::OneHot { columns, .. } => (2, columns),
+            FittedStep::LabelEncoding { columns, .. } => (3, columns)
+        };
+        data.push(tag);
+        data.extend_from_slice(&(columns.len() as u32).to_le_bytes());
+        for c in columns {
+            data.extend_from_slice(&(*c as u32).to_le_bytes());
+        }
+        match step {
+            FittedStep::Affine { scale, shift, .. } => {
+                for v in scale.iter().chain(shift) {
+                    data.extend_from_slice(&v.to_le_bytes());
+                }
+            },
+            FittedStep::Log { .. } => (),
+            FittedStep::OneHot { categories, .. } | FittedStep::LabelEncoding { categories, .. } => {
+                for column in categories {
+                    data.extend_from_slice(&(column.len() as u32).to_le_bytes());
+                    for v in column {
+                        data.extend_from_slice(&v.to_le_bytes());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Read a pipeline written by write_pipeline. Whether it fits the network is checked when the network is built.
+fn read_pipeline(reader : &mut BinaryReader) -> Result<Pipeline, StorageError> {
+    let input_size = reader.read_u32()? as usize;
+    let step_count = reader.read_u32()? as usize;
+    let mut steps = vec![];
+    for _s in 0..step_count {
+        let tag = reader.take(1)?[0];
+        let column_count = reader.read_u32()? as usize;
+        let mut columns = vec![];
+        for _c in 0..column_count {
+            columns.push(reader.read_u32()? as usize);
+        }
+        steps.push(match tag {
+            0 => {
+                let scale = reader.read_f64s(column_count)?;
+                let shift = reader.read_f64s(column_count)?;
+                FittedStep::Affine { columns, scale, shift }
+            },
+            1 => FittedStep::Log { columns },
+            2 | 3 => {
+                let mut categories = vec![];
+                for _c in 0..column_count {
+                    let count = reader.read_u32()? as usize;
+                    categories.push(reader.read_f64s(count)?);
+                }
+                if tag == 2 {
+                    FittedStep::OneHot { columns, categories }
+                }
+                else {
+                    FittedStep::LabelEncoding { columns, categories }
+                }
+            },
+            _ => return Err(StorageError::Corrupt(format!("unknown preprocessing tag {}", tag)))
+        });
+    }
+    return Ok(Pipeline::from_steps(input_size, steps));
+}
+
+/// Reads values from the front of a byte slice and fails if the data ends early
+struct BinaryReader<'a> {
+    data : &'a [u8],
+    position : usize
+}
+
+impl<'a> BinaryReader<'a> {
+    fn take(&mut self, count : usize) -> Result<&'a [u8], StorageError> {
+        if self.data.len() - self.position < count {
+            return Err(StorageError::Corrupt("unexpected end of data".to_string()));
+        }
+        let bytes = &self.data[self.position..self.position+count];
+        self.position += count;
+        return Ok(bytes);
+    }
+
+    fn read_u32(&mut self) -> Result<u32, StorageError> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        return Ok(u32::from_le_bytes(bytes));
+    }
+
+    fn read_f64(&mut self) -> Result<f64, StorageError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        return Ok(f64::from_le_bytes(bytes));
+    }
+
+    /// Read count values, failing before allocating them if the data is too short
+    fn read_f64s(&mut self, count : usize) -> Result<Vec<f64>, StorageError> {
+        if (self.data.len() - self.position) / 8 < count {
+            return Err(StorageError::Corrupt("unexpected end of data".to_string()));
+        }
+        let mut values = vec![];
+        for _i in 0..count {
+            values.push(self.read_f64()?);
+        }
+        return Ok(values);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::network::{Initializer, PreprocessingStep, Preprocessor};
+    use crate::trainer::TrainingData;
+
+    /// Network with every kind of stored value: parameterized activations, random weights, biases and both pipelines
+    fn example_network() -> NeuralNetwork {
+        let mut network = NeuralNetwork::with_initializer(vec![2, 4, 3], vec![Activation::LeakyRelu(0.1), Activation::Softmax], Initializer::XavierNormal, 7).unwrap();
+        network.set_biases(vec![vec![0.0, 0.0], vec![0.1, -0.2, 0.3, -0.4], vec![0.5, 0.25, -0.125]]).unwrap();
+        let training_set : Vec<TrainingData> = (0..6).map(|i| TrainingData::new(vec![i as f64, 10.0 * i as f64], vec![(i % 3) as f64])).collect();
+        network.fit_pipelines(&training_set, &[PreprocessingStep::all(Preprocessor::ZScore)], &[PreprocessingStep::all(Preprocessor::OneHot)]).unwrap();
+        return network;
+    }
+
+    fn assert_same_network(a : &NeuralNetwork, b : &NeuralNetwork) {
+        assert_eq!(a.get_structure(), b.get_structure());
+        assert_eq!(a.activations, b.activations);
+        assert_eq!(a.weights, b.weights);
+        assert_eq!(a.biases, b.biases);
+        assert_eq!(a.input_pipeline, b.input_pipeline);
+        assert_eq!(a.output_pipeline, b.output_pipeline);
+    }
+
+    /// A version 1 file: the current binary format without the two pipelines at the end
+    fn version_one_binary(network : &NeuralNetwork) -> Vec<u8> {
+        let mut plain = network.clone();
+        plain.input_pipeline = Pipeline::new();
+        plain.output_pipeline = Pipeline::new();
+        let mut data = plain.to_binary().unwrap();
+        data.truncate(data.len() - 16);
+        data[4..8].copy_from_slice(&1u32.to_le_bytes());
+        return data;
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let network = example_network();
+        for (format, extension) in [(StorageFormat::Json, "json"), (StorageFormat::Binary, "bin")] {
+            let path = std::env::temp_dir().join(format!("neural_net_storage_test_{}.{}", std::process::id(), extension));
+            network.save(&path, format).unwrap();
+            let loaded = NeuralNetwork::load(&path);
+            fs::remove_file(&path).unwrap();
+
+            let loaded = loaded.unwrap();
+            assert_same_network(&network, &loaded);
+            assert_eq!(network.predict(&[2.5, 7.0]).unwrap(), loaded.predict(&[2.5, 7.0]).unwrap());
+        }
+    }
+
+    #[test]
+    fn version_one_files_load_without_pipelines() {
+        let network = example_network();
+
+        let loaded = NeuralNetwork::from_binary(&version_one_binary(&network)).unwrap();
+        assert_eq!(loaded.weights, network.weights);
+        assert_eq!(loaded.biases, network.biases);
+        assert!(loaded.input_pipeline.is_empty() && loaded.output_pipeline.is_empty());
+
+        let mut json : serde_json::Value = serde_json::from_str(&network.to_json().unwrap()).unwrap();
+        let object = json.as_object_mut().unwrap();
+        object.insert("version".to_string(), 1.into());
+        object.remove("input_pipeline");
+        object.remove("output_pipeline");
+        let loaded = NeuralNetwork::from_json(&json.to_string()).unwrap();
+        assert_eq!(loaded.weights, network.weights);
+        assert!(loaded.input_pipeline.is_empty() && loaded.output_pipeline.is_empty());
+    }
+
+    #[test]
+    fn truncated_and_trailing_binary_data_is_corrupt() {
+        let data = example_network().to_binary().unwrap();
+        for length in [5, 10, 20, data.len() / 2, data.len() - 9, data.len() - 1] {
+            assert!(matches!(NeuralNetwork::from_binary(&data[..length]), Err(StorageError::Corrupt(_))), "length {}", length);
+        }
+
+        let mut trailing = data.clone();
+        trailing.push(0);
+        assert!(matches!(NeuralNetwork::from_binary(&trailing), Err(StorageError::Corrupt(_))));
+
+        // The activation tag of the first layer follows the magic, version, layer count and three layer sizes
+        let mut bad_activation = data;
+        bad_activation[24] = 200;
+        assert!(matches!(NeuralNetwork::from_binary(&bad_activation), Err(StorageError::Corrupt(_))));
+    }
+
+    #[test]
+    fn corrupt_json_is_rejected() {
+        let network = example_network();
+        let mut json : serde_json::Value = serde_json::from_str(&network.to_json().unwrap()).unwrap();
+        json["weights"][0][1] = serde_json::json!([1.0]);
+        assert!(matches!(NeuralNetwork::from_json(&json.to_string()), Err(StorageError::ShapeMismatch(NetworkError::InconsistentWeights { layer: 0, .. }))));
+
+        assert!(matches!(NeuralNetwork::from_json("{\"format\": \"neural-network\", \"version\": 2"), Err(StorageError::Json(_))));
+        assert!(matches!(NeuralNetwork::from_json("{\"format\": \"something else\", \"version\": 2}"), Err(StorageError::UnknownFormat)));
+        assert!(matches!(NeuralNetwork::from_json("{\"format\": \"neural-network\"}"), Err(StorageError::Corrupt(_))));
+
+        // A pipeline that does not produce the values of the input layer
+        let mut json : serde_json::Value = serde_json::from_str(&network.to_json().unwrap()).unwrap();
+        json["input_pipeline"]["input_size"] = 5.into();
+        assert!(matches!(NeuralNetwork::from_json(&json.to_string()), Err(StorageError::Corrupt(_))));
+    }
+
+    #[test]
+    fn other_versions_and_formats_are_rejected() {
+        let network = example_network();
+
+        let mut binary = network.to_binary().unwrap();
+        binary[4..8].copy_from_slice(&99u32.to_le_bytes());
+        assert!(matches!(NeuralNetwork::from_binary(&binary), Err(StorageError::UnsupportedVersion(99))));
+
+        let json = network.to_json().unwrap().replace("\"version\": 2", "\"version\": 99");
+        assert!(matches!(NeuralNetwork::from_json(&json), Err(StorageError::UnsupportedVersion(99))));
+
+        assert!(matches!(NeuralNetwork::from_binary(b"PNG\0 not a network"), Err(StorageError::UnknownFormat)));
+        let path = std::env::temp_dir().join(format!("neural_net_storage_test_{}.txt", std::process::id()));
+        fs::write(&path, "just some text").unwrap();
+        let loaded = NeuralNetwork::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(loaded, Err(StorageError::UnknownFormat)));
+    }
+
+    #[test]
+    fn non_finite_parameters_are_not_saved() {
+        let mut network = example_network();
+        network.weights[1][(2, 0)] = f64::NAN;
+        assert!(matches!(network.to_json(), Err(StorageError::ShapeMismatch(NetworkError::NonFiniteValue("weight")))));
+        assert!(matches!(network.to_binary(), Err(StorageError::ShapeMismatch(NetworkError::NonFiniteValue("weight")))));
+
+        let mut network = example_network();
+        network.biases[1][0] = f64::INFINITY;
+        let path = std::env::temp_dir().join(format!("neural_net_storage_test_{}_nan.json", std::process::id()));
+        assert!(matches!(network.save(&path, StorageFormat::Json), Err(StorageError::ShapeMismatch(NetworkError::NonFiniteValue("bias")))));
+        assert!(!path.exists());
+
+        let mut network = example_network();
+        network.activations[0] = Activation::Elu(f64::NAN);
+        assert!(matches!(network.to_json(), Err(StorageError::ShapeMismatch(NetworkError::NonFiniteValue("activation parameter")))));
+    }
+
+    #[test]
+    fn json_structure_is_checked_before_allocating() {
+        let json = r#"{"format":"neural-network","version":2,"structure":[3000000000,3000000000],"activations":["Sigmoid"],"weights":[],"biases":[]}"#;
+        assert!(matches!(NeuralNetwork::from_json(json), Err(StorageError::ShapeMismatch(NetworkError::LayerCountMismatch { .. }))));
+
+        let json = r#"{"format":"neural-network","version":2,"structure":[2,3000000000],"activations":["Sigmoid"],"weights":[[[1.0],[1.0]]],"biases":[[0.0,0.0],[0.0]]}"#;
+        assert!(matches!(NeuralNetwork::from_json(json), Err(StorageError::ShapeMismatch(NetworkError::InconsistentBiases { layer: 1, .. }))));
+    }
+}
@@ -1,120 +1,173 @@
+pub mod callback;
+pub mod cross_validation;
+pub mod genetic;
+pub mod gradient;
+pub mod loss;
+pub mod optimizer;
+pub mod schedule;
+pub mod validation;
+
+pub use self::callback::{CallbackAction, GenerationInfo, ProgressPrinter, TrainingCallback};
+pub use self::cross_validation::{CrossValidationConfig, CrossValidationMetrics, CrossValidationResult, FoldResult, FoldTrainingFunction, MetricSummary};
+pub use self::genetic::{Crossover, GeneticConfig, Selection};
+pub use self::gradient::{BatchSize, GradientConfig};
+pub use self::loss::Loss;
+pub use self::optimizer::{Adagrad, Adam, Gradients, Momentum, Nesterov, Optimizer, RmsProp, Sgd};
+pub use self::schedule::Schedule;
+pub use self::validation::ValidationConfig;
+
+use rand::StdRng;
+
+/// Function that scores a network, higher scores are better. Gets the training set the trainer was called with
+/// and a random number generator seeded from the seed of the training run, to be used for all randomness of the evaluation.
+pub type EvaluationFunction<'a> = dyn FnMut(&[TrainingData], &mut super::network::NeuralNetwork, &mut StdRng) -> Result<f64, super::network::NetworkError> + 'a;
+
+/// Evaluation function that can run on several threads at once. Higher scores are better.
+pub type ParallelEvaluationFunction<'a> = dyn Fn(&[TrainingData], &super::network::NeuralNetwork, &mut StdRng) -> Result<f64, super::network::NetworkError> + Sync + 'a;
+
 pub struct Trainer {
-    
+    callbacks : Vec<Box<dyn TrainingCallback>>
+}
+
+impl Default for Trainer {
+    fn default() -> Trainer {
+        return Trainer::new();
+    }
 }
 
 impl Trainer {
     pub fn new() -> Trainer {
         let t = Trainer {
-            
+            callbacks: vec![]
         };
         return t;
     }
 
     /// Evaluate a give network with all datasets in the training set and return the score.
-    pub fn evaluate_with_training_data(training_set : &Vec<TrainingData>, network : &mut super::network::NeuralNetwork) -> f64 {
+    pub fn evaluate_with_training_data(training_set : &[TrainingData], network : &mut super::network::NeuralNetwork) -> Result<f64, super::network::NetworkError> {
+        return Trainer::evaluate_with_workspace(training_set, network, &mut super::network::BatchWorkspace::new());
+    }
+
+    /// Evaluate a give network with all datasets in the training set as one batch and return the score.
+    /// The workspace keeps its buffers between calls, so evaluating many networks does not allocate them again.
+    /// The training set holds values of the input and output layers, see NeuralNetwork::preprocess_training_set.
+    pub fn evaluate_with_workspace(training_set : &[TrainingData], network : &super::network::NeuralNetwork, workspace : &mut super::network::BatchWorkspace) -> Result<f64, super::network::NetworkError> {
+        if training_set.is_empty() {
+            return Ok(0.0);
+        }
+        Trainer::check_training_set(training_set, network)?;
+
+        let outputs = network.forward_batch(&Trainer::input_matrix(training_set), workspace)?;
+
         let mut score = 0.0;
-        for ts in training_set {
-            network.set_inputs(ts.input.clone());
-            network.calculate_network();
-            let output = network.get_outputs();
+        for (t, ts) in training_set.iter().enumerate() {
+            let output = outputs.row(t);
             for n in 0..output.len() {
                 score -= (ts.output[n] - output[n]).abs();
             }
         }
 
-        return score.clone();
+        return Ok(score);
     }
 
-    /// Train the network using a genetic algorithm. Evaluation using the training set.
-    pub fn train_genetic_algorithm_dataset (&mut self, network : &super::network::NeuralNetwork, training_set : &Vec<TrainingData>, generations : usize, population : usize, mutation_start : f64, mutation_change_mult : f64) -> TrainingResult {
-        return self.train_genetic_algorithm_custom(network, training_set, generations, population, mutation_start, mutation_change_mult, &mut Trainer::evaluate_with_training_data);
+    /// Evaluate a give network with all datasets in the training set as one batch and return the negative mean loss as the score.
+    /// The training set holds values of the input and output layers, see NeuralNetwork::preprocess_training_set.
+    pub fn evaluate_with_loss(training_set : &[TrainingData], network : &super::network::NeuralNetwork, loss : &Loss, workspace : &mut super::network::BatchWorkspace) -> Result<f64, super::network::NetworkError> {
+        if training_set.is_empty() {
+            return Ok(0.0);
+        }
+        Trainer::check_training_set(training_set, network)?;
+        loss.check(network.nodes[network.nodes.len()-1].len())?;
+        return Trainer::evaluate_inputs_with_loss(training_set, &Trainer::input_matrix(training_set), network, loss, workspace);
     }
 
-    /// Train the network using a genetic algorithm. Evaluation using the given evaluation function.
-    pub fn train_genetic_algorithm_custom (&mut self, network : &super::network::NeuralNetwork, training_set : &Vec<TrainingData>,  generations : usize, population : usize, mutation_start : f64, mutation_change_mult : f64, evaluation_function : &mut dyn FnMut(&Vec<TrainingData>, &mut super::network::NeuralNetwork) -> f64) -> TrainingResult {
-        let mut parent_network = super::network::NeuralNetwork::new(vec![1,1]);
-        parent_network.initialize(network.get_structure());
-        parent_network.set_weights(network.weights.clone());
-        parent_network.set_biases(network.biases.clone());
-
-        let mut current_net = super::network::NeuralNetwork::new(vec![1,1]);
-        let mut current_score = -1000000.0;
-
-        let mut mutation_ammount = mutation_start; 
-
-        let mut generation_scores = vec![];
-
-        println!("Training Network using custom function...");
-        for _i in 0..generations {
-            for _p in 0..population {
-                current_net.initialize(parent_network.get_structure());
-                current_net.set_weights(parent_network.weights.clone());
-                current_net.set_biases(parent_network.biases.clone());
-
-                current_net.mutate_weights(mutation_ammount);
-                current_net.mutate_biases(mutation_ammount);
-
-                let score = evaluation_function(training_set, &mut current_net).clone();
-                if score > current_score {
-                    parent_network.set_weights(current_net.weights.clone());
-                    current_score = score;
-                }
-            }
-            mutation_ammount *= mutation_change_mult;
-            generation_scores.push(current_score);
+    /// Like evaluate_with_loss, but with the inputs of the training set already in a matrix, one row per sample.
+    /// Does not check the training set and loss, so runs that evaluate many networks check them once and copy the inputs once.
+    fn evaluate_inputs_with_loss(training_set : &[TrainingData], inputs : &super::network::Matrix, network : &super::network::NeuralNetwork, loss : &Loss, workspace : &mut super::network::BatchWorkspace) -> Result<f64, super::network::NetworkError> {
+        if training_set.is_empty() {
+            return Ok(0.0);
+        }
+        let outputs = network.forward_batch(inputs, workspace)?;
 
-            if _i % (generations / 10) == 0 && _i > 0 {
-                println!("{}%", _i*100/generations);
-            }
+        let mut total = 0.0;
+        for (t, ts) in training_set.iter().enumerate() {
+            total += loss.calculate(outputs.row(t), &ts.output);
         }
-        println!("Done! Final score: {0}", generation_scores[generation_scores.len()-1]);
 
-        let mut output_network = super::network::NeuralNetwork::new(vec![1,1]);
-        output_network.initialize(network.get_structure());
-        output_network.set_weights(current_net.weights.clone());
-        output_network.set_biases(current_net.biases.clone());
+        return Ok(-total / training_set.len() as f64);
+    }
 
-        return TrainingResult {
-            network:output_network,
-            generation_score_curve: generation_scores
-        };
+    /// Inputs of the training set as a matrix, one row per sample. The inputs must all have the same size.
+    fn input_matrix(training_set : &[TrainingData]) -> super::network::Matrix {
+        return super::network::Matrix::from_rows(training_set.iter().map(|ts| ts.input.clone()).collect());
     }
 
-    pub fn train_backpropagation (&mut self, network : &super::network::NeuralNetwork, training_set : &Vec<TrainingData>,  generations : usize) -> TrainingResult {
-        self.calculate_errors(network, vec![0.0; network.nodes[network.nodes.len()-1].len()]);
-        return TrainingResult{
-            network: super::network::NeuralNetwork::new(vec![2,2]),
-            generation_score_curve: vec![0.0]
+    /// Check that the inputs and outputs of every entry in the training set fit the network
+    pub fn check_training_set(training_set : &[TrainingData], network : &super::network::NeuralNetwork) -> Result<(), super::network::NetworkError> {
+        for ts in training_set {
+            network.check_input(&ts.input)?;
+            network.check_output(&ts.output)?;
         }
+        return Ok(());
+    }
+
+    /// Train the network using a genetic algorithm. Evaluation using the training set.
+    /// This is a hill climber, see train_genetic_population_dataset for crossover and selection.
+    /// The children of every generation are evaluated in parallel on all available cores.
+    pub fn train_genetic_algorithm_dataset (&mut self, network : &super::network::NeuralNetwork, training_set : &[TrainingData], generations : usize, population : usize, mutation_start : f64, mutation_change_mult : f64) -> Result<TrainingResult, super::network::NetworkError> {
+        return self.train_genetic_population_dataset(network, training_set, &[], &GeneticConfig::hill_climber(generations, population, mutation_start, mutation_change_mult));
+    }
+
+    /// Train the network using a genetic algorithm. Evaluation using the given evaluation function.
+    /// Every generation creates population mutated children of the best network so far, which replace it if they score higher.
+    /// See train_genetic_population_custom for crossover and selection.
+    /// The evaluation function may change the networks, so they are evaluated one at a time on the calling thread.
+    /// Use train_genetic_population_parallel with GeneticConfig::hill_climber to evaluate on several threads.
+    #[allow(clippy::too_many_arguments)]
+    pub fn train_genetic_algorithm_custom (&mut self, network : &super::network::NeuralNetwork, training_set : &[TrainingData],  generations : usize, population : usize, mutation_start : f64, mutation_change_mult : f64, evaluation_function : &mut EvaluationFunction) -> Result<TrainingResult, super::network::NetworkError> {
+        return self.train_genetic_population_custom(network, training_set, &[], &GeneticConfig::hill_climber(generations, population, mutation_start, mutation_change_mult), evaluation_function);
+    }
+
+    /// Train the network using gradient descent with backpropagation. Every epoch runs over the whole training set,
+    /// averages the gradients of all samples and steps the weights and biases by the learning rate.
+    /// The score curve holds the mean squared error of each epoch, negated like the scores of the genetic trainers.
+    /// See train_with_optimizer for mini-batches, other losses and optimizers.
+    pub fn train_backpropagation (&mut self, network : &super::network::NeuralNetwork, training_set : &[TrainingData], epochs : usize, learning_rate : f64) -> Result<TrainingResult, super::network::NetworkError> {
+        let config = GradientConfig { epochs, shuffle: false, ..GradientConfig::default() };
+        return self.train_with_optimizer(network, training_set, &[], &config, &mut Sgd::new(learning_rate));
     }
 
-    pub fn calculate_errors(&mut self, network : &super::network::NeuralNetwork, expected_outputs : Vec<f64>) -> Vec<Vec<f64>> {
-        let mut errors : Vec<Vec<f64>> = network.nodes.clone(); // Clone the network nodes to get a matrix in the correct size
+    /// Calculate the gradients of the loss, averaged over the training set, and the mean loss itself
+    pub fn calculate_gradients(&mut self, network : &mut super::network::NeuralNetwork, training_set : &[TrainingData], loss : &Loss) -> Result<(Gradients, f64), super::network::NetworkError> {
+        Trainer::check_training_set(training_set, network)?;
+        let batch : Vec<&TrainingData> = training_set.iter().collect();
+        return self.calculate_batch_gradients(network, &batch, loss, &mut super::network::BatchWorkspace::new());
+    }
+
+    /// Calculate the error of every node for the given expected outputs, using the node values cached by the last run of calculate_network.
+    /// The error of a node is the derivative of the loss with respect to the weighted sum of that node,
+    /// using the derivative of the activation function of its layer. The input layer has no weighted sum and is left at zero.
+    pub fn calculate_errors(&mut self, network : &super::network::NeuralNetwork, expected_outputs : &[f64], loss : &Loss) -> Result<Vec<Vec<f64>>, super::network::NetworkError> {
+        network.check_output(expected_outputs)?;
+        loss.check(expected_outputs.len())?;
+        let mut errors : Vec<Vec<f64>> = network.nodes.iter().map(|l| vec![0.0; l.len()]).collect(); // Matrix in the size of the network nodes
 
         let last_layer_index = errors.len()-1;
         // Calculate output errors
-        for n in 0..errors[last_layer_index].len() {
-            errors[last_layer_index][n] = expected_outputs[n] - network.nodes[network.nodes.len()-1][n];
-        }
-        
-        for layer in 0..errors.len()-1 {
-            for node in 0..errors[layer].len() {
-                for w in 0..network.weights[layer][node].len() {
-                    let mut weightSum = 0.0;
-                    for n in 0..network.weights[layer].len() {
-                        weightSum += network.weights[layer][n][w]
-                    }
-                    errors[layer][node] = (network.weights[layer][node][w] / weightSum) * errors[layer+1][w];
-                }
-            }
+        errors[last_layer_index] = loss.output_errors(&network.activations[last_layer_index-1], &network.weighted_sums[last_layer_index], &network.nodes[last_layer_index], expected_outputs);
+
+        // Propagate the errors back through the hidden layers
+        for layer in (1..last_layer_index).rev() {
+            errors[layer] = network.weights[layer].multiply_vector(&errors[layer+1]);
+            network.activations[layer-1].backpropagate_layer(&network.weighted_sums[layer], &network.nodes[layer], &mut errors[layer]);
         }
-        
 
-        return errors;
+        return Ok(errors);
     }
 }
 
 /// Data for training a network. Contains the inputs and the expected outputs for this input.
+#[derive(Clone, Debug, PartialEq)]
 pub struct TrainingData {
     input: Vec<f64>,
     output: Vec<f64>
@@ -123,7 +176,13 @@ pub struct TrainingData {
 /// Result of training a network. Contains the trained network and a curve of the scores over all training generations.
 pub struct TrainingResult {
     pub network : super::network::NeuralNetwork,
-    pub generation_score_curve : Vec<f64>
+    pub generation_score_curve : Vec<f64>,
+    /// Learning rate or mutation ammount used in every generation
+    pub rate_curve : Vec<f64>,
+    /// Negated validation loss, or the validation score of the custom evaluation, after every generation. Empty without a validation set.
+    pub validation_score_curve : Vec<f64>,
+    /// Generation with the best validation score. The returned network is from this generation if the config restores the best network.
+    pub best_generation : Option<usize>
 }
 
 /// Data containing one set of inputs and the expected outputs for them. 
@@ -131,7 +190,17 @@ impl TrainingData {
     pub fn new(inputs : Vec<f64>, output : Vec<f64>) -> TrainingData {
         return TrainingData {
             input: inputs,
-            output: output
+            output
         }
     }
+
+    /// Input values of the sample
+    pub fn input(&self) -> &[f64] {
+        return &self.input;
+    }
+
+    /// Expected output values of the sample
+    pub fn output(&self) -> &[f64] {
+        return &self.output;
+    }
 }
\ No newline at end of file
@@ -0,0 +1,202 @@
+use crate::network::NeuralNetwork;
+
+use super::{Trainer, TrainingResult};
+
+/// What the trainer should do after a callback was called
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CallbackAction {
+    Continue,
+    /// Stop training after the current generation or epoch
+    Stop
+}
+
+/// State of a training run at the end of a generation or epoch
+pub struct GenerationInfo<'a> {
+    /// Index of the generation or epoch that just ended
+    pub generation : usize,
+    /// Number of generations or epochs the run was started with
+    pub generations : usize,
+    /// Score of the best network of this generation, or the negated mean loss of this epoch
+    pub score : f64,
+    /// Validation score of this generation, if there is a validation set
+    pub validation_score : Option<f64>,
+    /// Mutation ammount or learning rate used in this generation
+    pub rate : f64,
+    /// Best network of this generation, or the network after this epoch
+    pub network : &'a NeuralNetwork
+}
+
+/// Hooks that get called while a network is trained. All hooks do nothing by default.
+/// Add callbacks to a trainer with Trainer::add_callback, they are called in the order they were added.
+pub trait TrainingCallback {
+    /// Called once before the first generation or epoch
+    fn on_training_start(&mut self, _network : &NeuralNetwork, _generations : usize) {}
+
+    /// Called after every generation or epoch
+    fn on_generation_end(&mut self, _info : &GenerationInfo) -> CallbackAction {
+        return CallbackAction::Continue;
+    }
+
+    /// Called after every optimizer step of gradient training with the mean loss of the batch
+    fn on_batch_end(&mut self, _epoch : usize, _batch : usize, _loss : f64) -> CallbackAction {
+        return CallbackAction::Continue;
+    }
+
+    /// Called once with the result before it is returned
+    fn on_training_end(&mut self, _result : &TrainingResult) {}
+}
+
+/// Prints the progress every 10% of the generations and the final score
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProgressPrinter {
+    generations : usize
+}
+
+impl ProgressPrinter {
+    pub fn new() -> ProgressPrinter {
+        return ProgressPrinter::default();
+    }
+}
+
+impl TrainingCallback for ProgressPrinter {
+    fn on_training_start(&mut self, _network : &NeuralNetwork, generations : usize) {
+        self.generations = generations;
+        println!("Training Network for {} generations...", generations);
+    }
+
+    fn on_generation_end(&mut self, info : &GenerationInfo) -> CallbackAction {
+        let g = info.generation;
+        if info.generations >= 10 && g.is_multiple_of(info.generations / 10) && g > 0 {
+            println!("{}%", g*100/info.generations);
+        }
+        return CallbackAction::Continue;
+    }
+
+    fn on_training_end(&mut self, result : &TrainingResult) {
+        if result.generation_score_curve.len() < self.generations {
+            println!("Stopped early after {} generations", result.generation_score_curve.len());
+        }
+        if let Some(score) = result.generation_score_curve.last() {
+            println!("Done! Final score: {0}", score);
+        }
+    }
+}
+
+impl Trainer {
+    /// Add a callback that gets called while training. A new trainer has no callbacks and trains silently,
+    /// add a ProgressPrinter to print the progress.
+    pub fn add_callback(&mut self, callback : Box<dyn TrainingCallback>) {
+        self.callbacks.push(callback);
+    }
+
+    /// Remove all callbacks, so training runs silently
+    pub fn clear_callbacks(&mut self) {
+        self.callbacks.clear();
+    }
+
+    pub(super) fn notify_training_start(&mut self, network : &NeuralNetwork, generations : usize) {
+        for callback in self.callbacks.iter_mut() {
+            callback.on_training_start(network, generations);
+        }
+    }
+
+    /// Returns true if any callback asked to stop
+    pub(super) fn notify_generation_end(&mut self, info : &GenerationInfo) -> bool {
+        let mut stop = false;
+        for callback in self.callbacks.iter_mut() {
+            stop |= callback.on_generation_end(info) == CallbackAction::Stop;
+        }
+        return stop;
+    }
+
+    /// Returns true if any callback asked to stop
+    pub(super) fn notify_batch_end(&mut self, epoch : usize, batch : usize, loss : f64) -> bool {
+        let mut stop = false;
+        for callback in self.callbacks.iter_mut() {
+            stop |= callback.on_batch_end(epoch, batch, loss) == CallbackAction::Stop;
+        }
+        return stop;
+    }
+
+    pub(super) fn notify_training_end(&mut self, result : &TrainingResult) {
+        for callback in self.callbacks.iter_mut() {
+            callback.on_training_end(result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::network::Activation;
+    use crate::trainer::{BatchSize, GeneticConfig, GradientConfig, Sgd, TrainingData};
+
+    /// Records every call and asks to stop at the given generation or batch
+    struct Recorder {
+        calls : Rc<RefCell<Vec<String>>>,
+        stop_generation : Option<usize>,
+        stop_batch : Option<(usize, usize)>
+    }
+
+    impl TrainingCallback for Recorder {
+        fn on_training_start(&mut self, _network : &NeuralNetwork, generations : usize) {
+            self.calls.borrow_mut().push(format!("start {}", generations));
+        }
+
+        fn on_generation_end(&mut self, info : &GenerationInfo) -> CallbackAction {
+            self.calls.borrow_mut().push(format!("generation {}", info.generation));
+            if self.stop_generation == Some(info.generation) {
+                return CallbackAction::Stop;
+            }
+            return CallbackAction::Continue;
+        }
+
+        fn on_batch_end(&mut self, epoch : usize, batch : usize, _loss : f64) -> CallbackAction {
+            self.calls.borrow_mut().push(format!("batch {} {}", epoch, batch));
+            if self.stop_batch == Some((epoch, batch)) {
+                return CallbackAction::Stop;
+            }
+            return CallbackAction::Continue;
+        }
+
+        fn on_training_end(&mut self, result : &TrainingResult) {
+            self.calls.borrow_mut().push(format!("end {}", result.generation_score_curve.len()));
+        }
+    }
+
+    fn recording_trainer(stop_generation : Option<usize>, stop_batch : Option<(usize, usize)>) -> (Trainer, Rc<RefCell<Vec<String>>>) {
+        let calls = Rc::new(RefCell::new(vec![]));
+        let mut trainer = Trainer::new();
+        trainer.add_callback(Box::new(Recorder { calls: calls.clone(), stop_generation, stop_batch }));
+        return (trainer, calls);
+    }
+
+    fn training_set() -> Vec<TrainingData> {
+        return (0..4).map(|i| TrainingData::new(vec![i as f64], vec![2.0 * i as f64])).collect();
+    }
+
+    #[test]
+    fn stop_ends_genetic_training() {
+        let network = NeuralNetwork::with_activations(vec![1, 1], vec![Activation::Linear]).unwrap();
+        let (mut trainer, calls) = recording_trainer(Some(2), None);
+        let config = GeneticConfig { generations: 10, population: 5, threads: 1, ..GeneticConfig::default() };
+        let result = trainer.train_genetic_population_dataset(&network, &training_set(), &[], &config).unwrap();
+
+        assert_eq!(result.generation_score_curve.len(), 3);
+        assert_eq!(*calls.borrow(), vec!["start 10", "generation 0", "generation 1", "generation 2", "end 3"]);
+    }
+
+    #[test]
+    fn stop_after_a_batch_finishes_the_epoch() {
+        let network = NeuralNetwork::with_activations(vec![1, 1], vec![Activation::Linear]).unwrap();
+        let (mut trainer, calls) = recording_trainer(None, Some((1, 0)));
+        let config = GradientConfig { epochs: 10, batch_size: BatchSize::Mini(2), shuffle: false, ..GradientConfig::default() };
+        let result = trainer.train_with_optimizer(&network, &training_set(), &[], &config, &mut Sgd::new(0.01)).unwrap();
+
+        assert_eq!(result.generation_score_curve.len(), 2);
+        assert_eq!(*calls.borrow(), vec!["start 10", "batch 0 0", "batch 0 1", "generation 0", "batch 1 0", "generation 1", "end 2"]);
+    }
+}
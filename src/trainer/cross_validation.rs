@@ -0,0 +1,287 @@
+use std::fmt;
+
+use rand::{Rng, SeedableRng, StdRng};
+
+use crate::evaluation::{ClassificationReport, RegressionReport};
+use crate::network::{NetworkError, NeuralNetwork};
+
+use super::{Trainer, TrainingData, TrainingResult};
+
+/// Function that trains the network of one fold. Gets the trainer, a fresh copy of the network and the training folds.
+/// The network can be changed before training, e.g. to fit its pipelines to the training folds only.
+pub type FoldTrainingFunction<'a> = dyn FnMut(&mut Trainer, &mut NeuralNetwork, &[TrainingData]) -> Result<TrainingResult, NetworkError> + 'a;
+
+/// Which metrics are measured on the test fold
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CrossValidationMetrics {
+    /// Accuracy, macro averaged precision, recall and F1, micro averaged F1, log-loss and ROC-AUC of a ClassificationReport
+    Classification,
+    /// MSE, RMSE, MAE, MAPE, R² and max error of every output of a RegressionReport
+    Regression
+}
+
+/// Settings for k-fold cross-validation
+#[derive(Clone, Debug, PartialEq)]
+pub struct CrossValidationConfig {
+    /// Number of folds, every fold is the test set once. Limited to the number of samples, which is leave-one-out.
+    pub folds : usize,
+    /// Give every fold about the same share of each class. Samples with equal expected outputs are one class.
+    pub stratify : bool,
+    /// Shuffle the samples before splitting them into folds, otherwise every fold is a contiguous part of the dataset
+    pub shuffle : bool,
+    /// Seed of the random number generator used for shuffling
+    pub seed : u64,
+    pub metrics : CrossValidationMetrics
+}
+
+impl Default for CrossValidationConfig {
+    fn default() -> CrossValidationConfig {
+        return CrossValidationConfig {
+            folds: 5,
+            stratify: false,
+            shuffle: true,
+            seed: 0,
+            metrics: CrossValidationMetrics::Regression
+        };
+    }
+}
+
+/// Training result and metrics of one fold
+pub struct FoldResult {
+    pub training_result : TrainingResult,
+    /// Indices of the samples in the test fold
+    pub test_indices : Vec<usize>,
+    /// Name and value of every metric measured on the test fold
+    pub metrics : Vec<(String, f64)>
+}
+
+/// Values of one metric over all folds
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetricSummary {
+    pub name : String,
+    /// Value of every fold that has the metric. ROC-AUC is missing for folds without both classes.
+    pub values : Vec<f64>,
+    pub mean : f64,
+    /// Sample standard deviation of the values, 0 for a single value
+    pub std : f64
+}
+
+/// Results of all folds of a cross-validation and the mean and standard deviation of every metric
+pub struct CrossValidationResult {
+    pub folds : Vec<FoldResult>,
+    pub metrics : Vec<MetricSummary>
+}
+
+impl Trainer {
+    /// Estimate how well a network generalizes with k-fold cross-validation. The dataset is split into folds
+    /// and for every fold a copy of the network is trained on the other folds by the training function,
+    /// then measured on the fold itself. The samples hold raw values, the metrics are measured like the reports of the evaluation module do.
+    /// Every fold starts from a clone of the given network, weights included, so pass an untrained network
+    /// or re-initialize it in the training function, e.g. with initialize_parameters.
+    pub fn cross_validate(&mut self, network : &NeuralNetwork, dataset : &[TrainingData], config : &CrossValidationConfig, train : &mut FoldTrainingFunction) -> Result<CrossValidationResult, NetworkError> {
+        if dataset.len() < 2 {
+            return Err(NetworkError::NotEnoughSamples { needed: 2, actual: dataset.len() });
+        }
+        let fold_count = config.folds.clamp(2, dataset.len());
+
+        let mut folds = vec![];
+        for test_indices in Trainer::split_folds(dataset, fold_count, config) {
+            let mut is_test = vec![false; dataset.len()];
+            for i in &test_indices {
+                is_test[*i] = true;
+            }
+            let training_set : Vec<TrainingData> = (0..dataset.len()).filter(|i| !is_test[*i]).map(|i| dataset[i].clone()).collect();
+            let test_set : Vec<TrainingData> = test_indices.iter().map(|i| dataset[*i].clone()).collect();
+
+            let mut fold_network = network.clone();
+            let training_result = train(self, &mut fold_network, &training_set)?;
+            let metrics = Trainer::fold_metrics(&training_result.network, &test_set, config.metrics)?;
+            folds.push(FoldResult { training_result, test_indices, metrics });
+        }
+
+        let metrics = Trainer::summarize_metrics(&folds);
+        return Ok(CrossValidationResult { folds, metrics });
+    }
+
+    /// Indices of the samples in every fold, each in dataset order
+    fn split_folds(dataset : &[TrainingData], fold_count : usize, config : &CrossValidationConfig) -> Vec<Vec<usize>> {
+        let mut order : Vec<usize> = (0..dataset.len()).collect();
+        if config.shuffle {
+            StdRng::from_seed(&[config.seed as usize]).shuffle(&mut order);
+        }
+
+        let mut folds = vec![vec![]; fold_count];
+        if config.stratify {
+            // Group the samples by class, keeping their order, then deal them out to the folds one after the other
+            let mut classes : Vec<(&[f64], Vec<usize>)> = vec![];
+            for i in order {
+                match classes.iter_mut().find(|(output, _)| *output == dataset[i].output()) {
+                    Some((_, members)) => members.push(i),
+                    None => classes.push((dataset[i].output(), vec![i]))
+                }
+            }
+            let dealt : Vec<usize> = classes.into_iter().flat_map(|(_, members)| members).collect();
+            for (position, i) in dealt.into_iter().enumerate() {
+                folds[position % fold_count].push(i);
+            }
+        }
+        else {
+            for f in 0..fold_count {
+                folds[f] = order[f * order.len() / fold_count..(f + 1) * order.len() / fold_count].to_vec();
+            }
+        }
+
+        for fold in folds.iter_mut() {
+            fold.sort_unstable();
+        }
+        return folds;
+    }
+
+    fn fold_metrics(network : &NeuralNetwork, test_set : &[TrainingData], kind : CrossValidationMetrics) -> Result<Vec<(String, f64)>, NetworkError> {
+        let mut metrics = vec![];
+        match kind {
+            CrossValidationMetrics::Classification => {
+                let report = ClassificationReport::evaluate(network, test_set)?;
+                metrics.push(("accuracy".to_string(), report.accuracy));
+                metrics.push(("precision (macro)".to_string(), report.macro_average.precision));
+                metrics.push(("recall (macro)".to_string(), report.macro_average.recall));
+                metrics.push(("f1 (macro)".to_string(), report.macro_average.f1));
+                metrics.push(("f1 (micro)".to_string(), report.micro_average.f1));
+                metrics.push(("log-loss".to_string(), report.log_loss));
+                if let Some(auc) = report.roc_auc {
+                    metrics.push(("roc-auc".to_string(), auc));
+                }
+            },
+            CrossValidationMetrics::Regression => {
+                let report = RegressionReport::evaluate(network, test_set)?;
+                for (name, m) in report.output_names.iter().zip(&report.outputs) {
+                    metrics.push((format!("mse ({})", name), m.mse));
+                    metrics.push((format!("rmse ({})", name), m.rmse));
+                    metrics.push((format!("mae ({})", name), m.mae));
+                    if let Some(mape) = m.mape {
+                        metrics.push((format!("mape ({})", name), mape));
+                    }
+                    metrics.push((format!("r2 ({})", name), m.r2));
+                    metrics.push((format!("max error ({})", name), m.max_error));
+                }
+            }
+        }
+        return Ok(metrics);
+    }
+
+    /// Mean and standard deviation of every metric, in the order they first appear
+    fn summarize_metrics(folds : &[FoldResult]) -> Vec<MetricSummary> {
+        let mut summaries : Vec<MetricSummary> = vec![];
+        for fold in folds {
+            for (name, value) in &fold.metrics {
+                match summaries.iter_mut().find(|s| s.name == *name) {
+                    Some(summary) => summary.values.push(*value),
+                    None => summaries.push(MetricSummary { name: name.clone(), values: vec![*value], mean: 0.0, std: 0.0 })
+                }
+            }
+        }
+
+        for summary in summaries.iter_mut() {
+            let n = summary.values.len() as f64;
+            summary.mean = summary.values.iter().sum::<f64>() / n;
+            if summary.values.len() > 1 {
+                summary.std = (summary.values.iter().map(|v| (v - summary.mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt();
+            }
+        }
+        return summaries;
+    }
+}
+
+impl fmt::Display for CrossValidationResult {
+    /// Print the mean, standard deviation and value of every fold for each metric as a table
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        let name_width = self.metrics.iter().map(|m| m.name.len()).max().unwrap_or(0).max(6);
+        write!(f, "{:<w$} {:>10} {:>10}", "Metric", "Mean", "Std", w = name_width)?;
+        for fold in 0..self.folds.len() {
+            write!(f, " {:>10}", format!("Fold {}", fold + 1))?;
+        }
+        writeln!(f)?;
+
+        for summary in &self.metrics {
+            write!(f, "{:<w$} {:>10.4} {:>10.4}", summary.name, summary.mean, summary.std, w = name_width)?;
+            for fold in &self.folds {
+                match fold.metrics.iter().find(|(name, _)| *name == summary.name) {
+                    Some((_, value)) => write!(f, " {:>10.4}", value)?,
+                    None => write!(f, " {:>10}", "-")?
+                }
+            }
+            writeln!(f)?;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::Activation;
+
+    /// Ten samples of two classes, 7 of class 0 and 3 of class 1
+    fn dataset() -> Vec<TrainingData> {
+        return (0..10).map(|i| TrainingData::new(vec![i as f64], vec![if i % 3 == 0 && i > 0 { 1.0 } else { 0.0 }])).collect();
+    }
+
+    #[test]
+    fn every_sample_is_in_exactly_one_fold() {
+        let dataset = dataset();
+        for stratify in [false, true] {
+            for shuffle in [false, true] {
+                let config = CrossValidationConfig { folds: 3, stratify, shuffle, seed: 7, ..CrossValidationConfig::default() };
+                let folds = Trainer::split_folds(&dataset, 3, &config);
+                assert_eq!(folds.len(), 3);
+                let mut all : Vec<usize> = folds.iter().flatten().copied().collect();
+                all.sort_unstable();
+                assert_eq!(all, (0..10).collect::<Vec<_>>());
+                assert!(folds.iter().all(|f| f.len() == 3 || f.len() == 4));
+            }
+        }
+
+        // Without shuffling the folds are contiguous
+        let config = CrossValidationConfig { shuffle: false, ..CrossValidationConfig::default() };
+        assert_eq!(Trainer::split_folds(&dataset, 3, &config), vec![vec![0, 1, 2], vec![3, 4, 5], vec![6, 7, 8, 9]]);
+    }
+
+    #[test]
+    fn stratified_folds_share_the_classes() {
+        let dataset = dataset();
+        let config = CrossValidationConfig { folds: 3, stratify: true, seed: 3, ..CrossValidationConfig::default() };
+        for fold in Trainer::split_folds(&dataset, 3, &config) {
+            assert_eq!(fold.iter().filter(|i| dataset[**i].output()[0] == 1.0).count(), 1);
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_folds() {
+        let dataset = dataset();
+        let config = |seed| CrossValidationConfig { folds: 4, seed, ..CrossValidationConfig::default() };
+        assert_eq!(Trainer::split_folds(&dataset, 4, &config(1)), Trainer::split_folds(&dataset, 4, &config(1)));
+        assert_ne!(Trainer::split_folds(&dataset, 4, &config(1)), Trainer::split_folds(&dataset, 4, &config(2)));
+    }
+
+    #[test]
+    fn cross_validate_trains_every_fold() {
+        let dataset = dataset();
+        let network = NeuralNetwork::with_activations(vec![1, 1], vec![Activation::Linear]).unwrap();
+        let config = CrossValidationConfig { folds: 20, ..CrossValidationConfig::default() };
+        let mut training_sizes = vec![];
+        let result = Trainer::new().cross_validate(&network, &dataset, &config, &mut |_, net, set| {
+            training_sizes.push(set.len());
+            return Ok(TrainingResult { network: net.clone(), generation_score_curve: vec![], rate_curve: vec![], validation_score_curve: vec![], best_generation: None });
+        }).unwrap();
+
+        // More folds than samples is leave-one-out
+        assert_eq!(result.folds.len(), 10);
+        assert_eq!(training_sizes, vec![9; 10]);
+        let mse = result.metrics.iter().find(|m| m.name == "mse (0)").unwrap();
+        assert_eq!(mse.values.len(), 10);
+        assert!((mse.mean - mse.values.iter().sum::<f64>() / 10.0).abs() < 1e-12);
+
+        let too_small = Trainer::new().cross_validate(&network, &dataset[..1], &config, &mut |_, _, _| unreachable!());
+        assert!(matches!(too_small, Err(NetworkError::NotEnoughSamples { needed: 2, actual: 1 })));
+    }
+}
@@ -0,0 +1,567 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use rand::{Rng, SeedableRng, StdRng};
+
+use crate::network::{BatchWorkspace, NetworkError, NeuralNetwork};
+
+use super::{EvaluationFunction, GenerationInfo, Loss, ParallelEvaluationFunction, Schedule, Trainer, TrainingData, TrainingResult, ValidationConfig};
+use super::validation::ValidationTracker;
+
+/// How parents for the next generation are picked from the population
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Selection {
+    /// Best of the given number of randomly picked networks
+    Tournament(usize),
+    /// Chance proportional to the score, shifted so the worst network still has a small chance
+    Roulette,
+    /// Chance proportional to the rank, so the distance between scores does not matter
+    Rank,
+    /// Random pick from the given number of best networks
+    Truncation(usize)
+}
+
+/// How two parents are combined into a child
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Crossover {
+    /// Children are mutated copies of a single parent
+    None,
+    /// Every weight and bias is taken from a random parent
+    Uniform,
+    /// Every weight matrix and bias vector is cut at a random point, taking the values before it from the first parent and after it from the second
+    SinglePoint,
+    /// Weighted average of both parents with a random blend factor per child
+    Arithmetic
+}
+
+/// Settings of the population based genetic algorithm
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeneticConfig {
+    pub generations : usize,
+    /// Number of networks in every generation
+    pub population : usize,
+    /// Mutation ammount used in the first generation
+    pub mutation_start : f64,
+    /// How the mutation ammount changes over the generations, starting from mutation_start
+    pub mutation_schedule : Schedule,
+    pub selection : Selection,
+    pub crossover : Crossover,
+    /// Chance that a child is created by crossover instead of copying a single parent
+    pub crossover_rate : f64,
+    /// Number of best networks copied into the next generation without changes
+    pub elitism : usize,
+    /// Loss used to score the networks when training with a dataset
+    pub loss : Loss,
+    /// Validation split and early stopping
+    pub validation : ValidationConfig,
+    /// Seed of the random number generator used for mutation, selection and crossover
+    pub seed : u64,
+    /// Number of threads for parallel evaluation, 0 uses all available cores.
+    /// The result does not depend on the number of threads.
+    pub threads : usize
+}
+
+/// Set a generation is scored on
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EvaluationSet {
+    Training,
+    Validation
+}
+
+impl EvaluationSet {
+    /// The value belonging to this set, e.g. the set itself or its inputs
+    fn select<'a, T: ?Sized>(&self, training : &'a T, validation : &'a T) -> &'a T {
+        return match *self {
+            EvaluationSet::Training => training,
+            EvaluationSet::Validation => validation
+        };
+    }
+}
+
+/// Scores a whole generation on one of the sets, one score for every network. Gets the seed for the random number generator of every evaluation.
+type PopulationEvaluation<'a> = dyn FnMut(EvaluationSet, &mut [NeuralNetwork], &[usize]) -> Result<Vec<f64>, NetworkError> + 'a;
+
+/// Scores one network on a worker thread. Gets the seed of the evaluation and the state the worker keeps for the whole run.
+type WorkerEvaluation<'a, W> = dyn Fn(&NeuralNetwork, usize, &mut W) -> Result<f64, NetworkError> + Sync + 'a;
+
+impl Default for GeneticConfig {
+    fn default() -> GeneticConfig {
+        return GeneticConfig {
+            generations: 200,
+            population: 100,
+            mutation_start: 1.0,
+            mutation_schedule: Schedule::Exponential(0.98),
+            selection: Selection::Tournament(3),
+            crossover: Crossover::Uniform,
+            crossover_rate: 0.7,
+            elitism: 2,
+            loss: Loss::MeanAbsoluteError,
+            validation: ValidationConfig::default(),
+            seed: 0,
+            threads: 0
+        };
+    }
+}
+
+impl GeneticConfig {
+    /// A (1+λ) hill climber: the best network is kept and the rest of the population are mutated copies of it.
+    /// The mutation ammount is multiplied by mutation_change_mult after every generation.
+    pub fn hill_climber(generations : usize, children : usize, mutation_start : f64, mutation_change_mult : f64) -> GeneticConfig {
+        return GeneticConfig {
+            generations,
+            population: children + 1,
+            mutation_start,
+            mutation_schedule: Schedule::Exponential(mutation_change_mult),
+            selection: Selection::Truncation(1),
+            crossover: Crossover::None,
+            crossover_rate: 0.0,
+            elitism: 1,
+            loss: Loss::MeanAbsoluteError,
+            validation: ValidationConfig::default(),
+            seed: 0,
+            threads: 0
+        };
+    }
+}
+
+impl Trainer {
+    /// Train the network using a population based genetic algorithm. Evaluation using the training set,
+    /// the score of a network is its negated mean loss. The population is evaluated in parallel.
+    /// Both sets hold raw values, which are preprocessed by the pipelines of the network before training.
+    pub fn train_genetic_population_dataset(&mut self, network : &NeuralNetwork, training_set : &[TrainingData], validation_set : &[TrainingData], config : &GeneticConfig) -> Result<TrainingResult, NetworkError> {
+        let (training_set, validation_set) = Trainer::validation_sets(training_set, validation_set, &config.validation);
+        let training_set = network.preprocess_training_set(&training_set)?;
+        let validation_set = network.preprocess_training_set(&validation_set)?;
+        Trainer::check_training_set(&training_set, network)?;
+        Trainer::check_training_set(&validation_set, network)?;
+        config.loss.check(network.nodes[network.nodes.len()-1].len())?;
+
+        // The sets are checked and copied into input matrices once, and every worker thread keeps its workspace for the whole run
+        let training_inputs = Trainer::input_matrix(&training_set);
+        let validation_inputs = Trainer::input_matrix(&validation_set);
+        let mut workspaces = vec![BatchWorkspace::new(); Trainer::thread_count(config.threads)];
+        return self.train_genetic_population(network, !validation_set.is_empty(), config, &mut |set, networks, seeds| {
+            let inputs = set.select(&training_inputs, &validation_inputs);
+            let set = set.select(&training_set, &validation_set);
+            return Trainer::evaluate_parallel(networks, seeds, &mut workspaces, &|net, _seed, workspace| Trainer::evaluate_inputs_with_loss(set, inputs, net, &config.loss, workspace));
+        });
+    }
+
+    /// Train the network using a population based genetic algorithm. Evaluation using the given evaluation function.
+    /// The first generation is the given network and mutated copies of it. Returns the best network found, so with 0 generations
+    /// this is the best network of the first generation, which is either the given network or a copy that scored higher.
+    /// If there is a validation set, or the config splits one off the training set, the best network of every generation
+    /// is also scored by the evaluation function with the validation set.
+    /// The networks are evaluated one at a time, see train_genetic_population_parallel for evaluation on several threads.
+    pub fn train_genetic_population_custom(&mut self, network : &NeuralNetwork, training_set : &[TrainingData], validation_set : &[TrainingData], config : &GeneticConfig, evaluation_function : &mut EvaluationFunction) -> Result<TrainingResult, NetworkError> {
+        let (training_set, validation_set) = Trainer::validation_sets(training_set, validation_set, &config.validation);
+        return self.train_genetic_population(network, !validation_set.is_empty(), config, &mut |set, networks, seeds| {
+            let set = set.select(&training_set, &validation_set);
+            let mut scores = vec![];
+            for (individual, seed) in networks.iter_mut().zip(seeds) {
+                scores.push(evaluation_function(set, individual, &mut StdRng::from_seed(&[*seed]))?);
+            }
+            return Ok(scores);
+        });
+    }
+
+    /// Train the network using a population based genetic algorithm, evaluating the networks of every generation on config.threads threads.
+    /// Works like train_genetic_population_custom, but the evaluation function can not change the networks.
+    /// Gives the same result as train_genetic_population_custom with the same seed and evaluation.
+    pub fn train_genetic_population_parallel(&mut self, network : &NeuralNetwork, training_set : &[TrainingData], validation_set : &[TrainingData], config : &GeneticConfig, evaluation_function : &ParallelEvaluationFunction) -> Result<TrainingResult, NetworkError> {
+        let (training_set, validation_set) = Trainer::validation_sets(training_set, validation_set, &config.validation);
+        let mut workers = vec![(); Trainer::thread_count(config.threads)];
+        return self.train_genetic_population(network, !validation_set.is_empty(), config, &mut |set, networks, seeds| {
+            let set = set.select(&training_set, &validation_set);
+            return Trainer::evaluate_parallel(networks, seeds, &mut workers, &|net, seed, _| evaluation_function(set, net, &mut StdRng::from_seed(&[seed])));
+        });
+    }
+
+    /// Run the genetic algorithm on training and validation sets that were already split, scoring the networks with the given evaluation.
+    /// The validation set is only used if validate is true.
+    fn train_genetic_population(&mut self, network : &NeuralNetwork, validate : bool, config : &GeneticConfig, evaluate_population : &mut PopulationEvaluation) -> Result<TrainingResult, NetworkError> {
+        let mut validation = ValidationTracker::new(&config.validation);
+        let mut rng = StdRng::from_seed(&[config.seed as usize]);
+        let population_size = config.population.max(1);
+        let elitism = config.elitism.min(population_size);
+
+        let mut mutation_ammount = config.mutation_schedule.value(config.mutation_start, 0, &[]);
+
+        // Start with the given network and mutated copies of it
+        let mut population = vec![network.clone()];
+        while population.len() < population_size {
+            let mut child = network.clone();
+            child.mutate_weights(mutation_ammount, &mut rng);
+            child.mutate_biases(mutation_ammount, &mut rng);
+            population.push(child);
+        }
+        let mut scores = Trainer::evaluate_population(EvaluationSet::Training, &mut population, &mut rng, evaluate_population)?;
+
+        let mut generation_scores = vec![];
+        let mut mutation_curve = vec![];
+
+        self.notify_training_start(network, config.generations);
+        for g in 0..config.generations {
+            mutation_ammount = config.mutation_schedule.value(config.mutation_start, g, &generation_scores);
+            mutation_curve.push(mutation_ammount);
+            let ranking = Trainer::rank_population(&scores);
+
+            // Elites move on unchanged and keep their scores
+            let mut next_population = vec![];
+            let mut next_scores = vec![];
+            for e in 0..elitism {
+                next_population.push(population[ranking[e]].clone());
+                next_scores.push(scores[ranking[e]]);
+            }
+
+            // Create all children first, so the random numbers do not depend on how the children are evaluated
+            let mut children = vec![];
+            while next_population.len() + children.len() < population_size {
+                let first = &population[Trainer::select_parent(&config.selection, &scores, &ranking, &mut rng)];
+                let mut child = if config.crossover != Crossover::None && rng.gen::<f64>() < config.crossover_rate {
+                    let second = &population[Trainer::select_parent(&config.selection, &scores, &ranking, &mut rng)];
+                    Trainer::crossover(&config.crossover, first, second, &mut rng)
+                }
+                else {
+                    first.clone()
+                };
+                child.mutate_weights(mutation_ammount, &mut rng);
+                child.mutate_biases(mutation_ammount, &mut rng);
+                children.push(child);
+            }
+            next_scores.extend(Trainer::evaluate_population(EvaluationSet::Training, &mut children, &mut rng, evaluate_population)?);
+            next_population.extend(children);
+
+            population = next_population;
+            scores = next_scores;
+
+            let best = Trainer::rank_population(&scores)[0];
+            generation_scores.push(scores[best]);
+
+            let mut stop = false;
+            let mut validation_score = None;
+            if validate {
+                let score = Trainer::evaluate_population(EvaluationSet::Validation, &mut [population[best].clone()], &mut rng, evaluate_population)?[0];
+                stop = validation.record(score, &population[best]);
+                validation_score = Some(score);
+            }
+
+            let info = GenerationInfo {
+                generation: g,
+                generations: config.generations,
+                score: scores[best],
+                validation_score,
+                rate: mutation_ammount,
+                network: &population[best]
+            };
+            if self.notify_generation_end(&info) || stop {
+                break;
+            }
+        }
+
+        let best = Trainer::rank_population(&scores)[0];
+        let result = TrainingResult {
+            network: validation.best_network.unwrap_or_else(|| population.swap_remove(best)),
+            generation_score_curve: generation_scores,
+            rate_curve: mutation_curve,
+            validation_score_curve: validation.scores,
+            best_generation: validation.best_generation
+        };
+        self.notify_training_end(&result);
+        return Ok(result);
+    }
+
+    /// Evaluate networks of the population. NaN scores are treated as the worst possible score.
+    /// Every evaluation gets its own seed, so the scores do not depend on the order the networks are evaluated in.
+    fn evaluate_population(set : EvaluationSet, networks : &mut [NeuralNetwork], rng : &mut StdRng, evaluate_population : &mut PopulationEvaluation) -> Result<Vec<f64>, NetworkError> {
+        if networks.is_empty() {
+            return Ok(vec![]);
+        }
+        let seeds : Vec<usize> = (0..networks.len()).map(|_| rng.gen::<usize>()).collect();
+        let mut scores = evaluate_population(set, networks, &seeds)?;
+        for score in scores.iter_mut() {
+            if score.is_nan() {
+                *score = f64::NEG_INFINITY;
+            }
+        }
+        return Ok(scores);
+    }
+
+    /// Number of threads used for parallel evaluation, 0 uses all available cores
+    fn thread_count(threads : usize) -> usize {
+        if threads == 0 {
+            return thread::available_parallelism().map_or(1, |n| n.get());
+        }
+        return threads;
+    }
+
+    /// Evaluate the networks on one thread per worker state, but not more threads than networks. Every thread takes the next network
+    /// that was not evaluated yet and passes its own worker state, e.g. a workspace, to the evaluation, so the states last for the whole run.
+    /// The scores are returned in the order of the networks. If evaluations fail, the error of the first failed network is returned.
+    /// The threads are spawned again for every generation. That takes microseconds, which is small next to scoring a generation,
+    /// and lets the threads borrow the networks of the generation instead of sending them to threads that live for the whole run.
+    fn evaluate_parallel<W: Send>(networks : &[NeuralNetwork], seeds : &[usize], workers : &mut [W], evaluate : &WorkerEvaluation<W>) -> Result<Vec<f64>, NetworkError> {
+        let threads = workers.len().min(networks.len());
+        if threads <= 1 {
+            let worker = &mut workers[0];
+            return (0..networks.len()).map(|i| evaluate(&networks[i], seeds[i], worker)).collect();
+        }
+
+        let next = &AtomicUsize::new(0);
+        let mut results : Vec<Option<Result<f64, NetworkError>>> = vec![None; networks.len()];
+        thread::scope(|scope| {
+            let mut handles = vec![];
+            for worker in workers.iter_mut().take(threads) {
+                handles.push(scope.spawn(move || {
+                    let mut evaluated = vec![];
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        if i >= networks.len() {
+                            return evaluated;
+                        }
+                        evaluated.push((i, evaluate(&networks[i], seeds[i], worker)));
+                    }
+                }));
+            }
+            for handle in handles {
+                match handle.join() {
+                    Ok(evaluated) => {
+                        for (i, result) in evaluated {
+                            results[i] = Some(result);
+                        }
+                    },
+                    Err(panic) => std::panic::resume_unwind(panic)
+                }
+            }
+        });
+
+        return results.into_iter().map(|r| r.unwrap()).collect();
+    }
+
+    /// Indices of the population sorted from the best to the worst score
+    fn rank_population(scores : &[f64]) -> Vec<usize> {
+        let mut ranking : Vec<usize> = (0..scores.len()).collect();
+        ranking.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]));
+        return ranking;
+    }
+
+    /// Pick the index of a parent from the population
+    fn select_parent<R: Rng>(selection : &Selection, scores : &[f64], ranking : &[usize], rng : &mut R) -> usize {
+        match *selection {
+            Selection::Tournament(size) => {
+                let mut best = rng.gen_range(0, scores.len());
+                for _t in 1..size {
+                    let contestant = rng.gen_range(0, scores.len());
+                    if scores[contestant] > scores[best] {
+                        best = contestant;
+                    }
+                }
+                return best;
+            },
+            Selection::Roulette => {
+                let worst = scores[ranking[ranking.len()-1]];
+                let best = scores[ranking[0]];
+                if !worst.is_finite() || !best.is_finite() || best == worst {
+                    return ranking[rng.gen_range(0, ranking.len())];
+                }
+                // Shift all scores above zero, giving the worst network a small share of the wheel
+                let offset = (best - worst) / scores.len() as f64;
+                let weights : Vec<f64> = scores.iter().map(|s| s - worst + offset).collect();
+                return Trainer::spin_wheel(&weights, rng);
+            },
+            Selection::Rank => {
+                let mut weights = vec![0.0; scores.len()];
+                for (rank, index) in ranking.iter().enumerate() {
+                    weights[*index] = (scores.len() - rank) as f64;
+                }
+                return Trainer::spin_wheel(&weights, rng);
+            },
+            Selection::Truncation(count) => {
+                return ranking[rng.gen_range(0, count.clamp(1, ranking.len()))];
+            }
+        }
+    }
+
+    /// Pick an index with a chance proportional to its weight
+    fn spin_wheel<R: Rng>(weights : &[f64], rng : &mut R) -> usize {
+        let total : f64 = weights.iter().sum();
+        let mut target = rng.gen::<f64>() * total;
+        for i in 0..weights.len() {
+            target -= weights[i];
+            if target < 0.0 {
+                return i;
+            }
+        }
+        return weights.len()-1;
+    }
+
+    /// Combine the weights and biases of two parents with the same structure into a child
+    fn crossover<R: Rng>(crossover : &Crossover, first : &NeuralNetwork, second : &NeuralNetwork, rng : &mut R) -> NeuralNetwork {
+        let mut child = first.clone();
+        let blend = rng.gen::<f64>();
+
+        let mut layers : Vec<(&mut [f64], &[f64])> = vec![];
+        for (child_weights, second_weights) in child.weights.iter_mut().zip(&second.weights) {
+            layers.push((child_weights.data_mut(), second_weights.data()));
+        }
+        for (child_biases, second_biases) in child.biases.iter_mut().zip(&second.biases) {
+            layers.push((child_biases, second_biases));
+        }
+
+        for (values, other) in layers {
+            match *crossover {
+                Crossover::None => (),
+                Crossover::Uniform => {
+                    for i in 0..values.len() {
+                        if rng.gen::<bool>() {
+                            values[i] = other[i];
+                        }
+                    }
+                },
+                Crossover::SinglePoint => {
+                    let cut = rng.gen_range(0, values.len() + 1);
+                    values[cut..].copy_from_slice(&other[cut..]);
+                },
+                Crossover::Arithmetic => {
+                    for i in 0..values.len() {
+                        values[i] = blend * values[i] + (1.0 - blend) * other[i];
+                    }
+                }
+            }
+        }
+
+        return child;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{Activation, Initializer};
+
+    fn xor_set() -> Vec<TrainingData> {
+        return vec![
+            TrainingData::new(vec![0.0, 0.0], vec![0.0]),
+            TrainingData::new(vec![0.0, 1.0], vec![1.0]),
+            TrainingData::new(vec![1.0, 0.0], vec![1.0]),
+            TrainingData::new(vec![1.0, 1.0], vec![0.0])
+        ];
+    }
+
+    fn train(threads : usize) -> TrainingResult {
+        let network = NeuralNetwork::with_initializer(vec![2, 4, 1], vec![Activation::Tanh, Activation::Sigmoid], Initializer::XavierUniform, 3).unwrap();
+        let config = GeneticConfig {
+            generations: 15,
+            population: 12,
+            validation: ValidationConfig { split: 0.25, seed: 5, ..ValidationConfig::default() },
+            seed: 42,
+            threads,
+            ..GeneticConfig::default()
+        };
+        return Trainer::new().train_genetic_population_dataset(&network, &xor_set(), &[], &config).unwrap();
+    }
+
+    fn assert_same_result(a : &TrainingResult, b : &TrainingResult) {
+        assert_eq!(a.generation_score_curve, b.generation_score_curve);
+        assert_eq!(a.rate_curve, b.rate_curve);
+        assert_eq!(a.validation_score_curve, b.validation_score_curve);
+        assert_eq!(a.best_generation, b.best_generation);
+        assert_eq!(a.network.weights, b.network.weights);
+        assert_eq!(a.network.biases, b.network.biases);
+    }
+
+    /// Network with every weight and bias set to value
+    fn filled_network(value : f64) -> NeuralNetwork {
+        let mut network = NeuralNetwork::with_initializer(vec![3, 4, 2], vec![Activation::Tanh, Activation::Linear], Initializer::Constant(value), 0).unwrap();
+        for layer in network.biases.iter_mut() {
+            for b in layer.iter_mut() {
+                *b = value;
+            }
+        }
+        return network;
+    }
+
+    /// All weights and biases of a network, one slice per layer
+    fn layers(network : &NeuralNetwork) -> Vec<Vec<f64>> {
+        return network.weights.iter().map(|w| w.data().to_vec()).chain(network.biases.iter().cloned()).filter(|l| !l.is_empty()).collect();
+    }
+
+    #[test]
+    fn selection_prefers_better_scores() {
+        let scores = [0.0, 3.0, 1.0, 2.0];
+        let ranking = Trainer::rank_population(&scores);
+        assert_eq!(ranking, vec![1, 3, 2, 0]);
+
+        let mut rng = StdRng::from_seed(&[1]);
+        let mut count = |selection : Selection, scores : &[f64]| {
+            let mut counts = vec![0; scores.len()];
+            for _ in 0..4000 {
+                counts[Trainer::select_parent(&selection, scores, &ranking, &mut rng)] += 1;
+            }
+            return counts;
+        };
+
+        assert_eq!(count(Selection::Tournament(40), &scores), vec![0, 4000, 0, 0]);
+        let truncation = count(Selection::Truncation(2), &scores);
+        assert_eq!(truncation[0] + truncation[2], 0);
+        assert!(truncation[1] > 1800 && truncation[3] > 1800);
+
+        // Roulette weights are 0.75, 3.75, 1.75 and 2.75, rank weights 1, 4, 2 and 3
+        for selection in [Selection::Roulette, Selection::Rank] {
+            let counts = count(selection, &scores);
+            assert!(counts[0] < counts[2] && counts[2] < counts[3] && counts[3] < counts[1], "{:?} {:?}", selection, counts);
+        }
+
+        // Equal or infinite scores give every network the same chance
+        let uniform = count(Selection::Roulette, &[1.0, 1.0, 1.0, f64::NEG_INFINITY]);
+        assert!(uniform.iter().all(|c| *c > 800));
+    }
+
+    #[test]
+    fn crossover_combines_both_parents() {
+        let (zeros, ones) = (filled_network(0.0), filled_network(1.0));
+        let mut rng = StdRng::from_seed(&[2]);
+
+        let copy = Trainer::crossover(&Crossover::None, &zeros, &ones, &mut rng);
+        assert_eq!(layers(&copy), layers(&zeros));
+
+        let uniform : Vec<f64> = layers(&Trainer::crossover(&Crossover::Uniform, &zeros, &ones, &mut rng)).concat();
+        assert!(uniform.iter().all(|v| *v == 0.0 || *v == 1.0));
+        assert!(uniform.contains(&0.0) && uniform.contains(&1.0));
+
+        // Every layer takes its start from the first parent and its end from the second
+        for layer in layers(&Trainer::crossover(&Crossover::SinglePoint, &zeros, &ones, &mut rng)) {
+            assert!(layer.windows(2).all(|w| w[0] <= w[1]), "{:?}", layer);
+        }
+
+        let arithmetic : Vec<f64> = layers(&Trainer::crossover(&Crossover::Arithmetic, &zeros, &ones, &mut rng)).concat();
+        assert!(arithmetic.iter().all(|v| *v == arithmetic[0] && (0.0..=1.0).contains(v)));
+    }
+
+    #[test]
+    fn no_generations_keeps_the_best_of_the_first_population() {
+        // The given network fits the data exactly, so no mutated copy can beat it
+        let mut network = NeuralNetwork::with_activations(vec![1, 1], vec![Activation::Linear]).unwrap();
+        network.set_weights(vec![crate::network::Matrix::from_vec(1, 1, vec![2.0])]).unwrap();
+        let training_set : Vec<TrainingData> = (0..4).map(|i| TrainingData::new(vec![i as f64], vec![2.0 * i as f64])).collect();
+        let config = GeneticConfig { generations: 0, population: 10, ..GeneticConfig::default() };
+        let result = Trainer::new().train_genetic_population_dataset(&network, &training_set, &[], &config).unwrap();
+        assert!(result.generation_score_curve.is_empty());
+        assert_eq!(result.network.weights, network.weights);
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_result() {
+        let first = train(1);
+        assert_eq!(first.generation_score_curve.len(), 15);
+        assert_eq!(first.validation_score_curve.len(), 15);
+        assert_same_result(&first, &train(1));
+    }
+
+    #[test]
+    fn result_does_not_depend_on_the_thread_count() {
+        let single = train(1);
+        assert_same_result(&single, &train(4));
+        assert_same_result(&single, &train(0));
+    }
+}
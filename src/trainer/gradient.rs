@@ -0,0 +1,252 @@
+use rand::{Rng, SeedableRng, StdRng};
+
+use crate::network::{BatchWorkspace, Matrix, NetworkError, NeuralNetwork};
+
+use super::{GenerationInfo, Gradients, Loss, Optimizer, Schedule, Trainer, TrainingData, TrainingResult, ValidationConfig};
+use super::validation::ValidationTracker;
+
+/// How many samples are used for every step of the optimizer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BatchSize {
+    /// One step per epoch over the whole training set
+    Full,
+    /// Steps over batches of the given number of samples
+    Mini(usize),
+    /// One step per sample
+    Stochastic
+}
+
+impl BatchSize {
+    /// Number of samples in a full batch for a training set of the given length
+    pub fn samples(&self, set_length : usize) -> usize {
+        let samples = match *self {
+            BatchSize::Full => set_length,
+            BatchSize::Mini(size) => size.min(set_length),
+            BatchSize::Stochastic => 1
+        };
+        return samples.max(1);
+    }
+}
+
+/// Settings of gradient based training
+#[derive(Clone, Debug, PartialEq)]
+pub struct GradientConfig {
+    pub epochs : usize,
+    /// Loss the gradients are calculated for
+    pub loss : Loss,
+    pub batch_size : BatchSize,
+    /// Shuffle the order of the training set at the start of every epoch
+    pub shuffle : bool,
+    /// Skip the samples left over after the last full batch of an epoch instead of stepping on a smaller batch.
+    /// Batches larger than the training set are shrunk to its size, so there is always one full batch.
+    pub drop_last : bool,
+    /// Seed of the random number generator used for shuffling
+    pub seed : u64,
+    /// How the learning rate changes over the epochs, starting from the learning rate of the optimizer
+    pub learning_rate_schedule : Schedule,
+    /// Validation split and early stopping
+    pub validation : ValidationConfig
+}
+
+impl Default for GradientConfig {
+    fn default() -> GradientConfig {
+        return GradientConfig {
+            epochs: 1000,
+            loss: Loss::MeanSquaredError,
+            batch_size: BatchSize::Full,
+            shuffle: true,
+            drop_last: false,
+            seed: 0,
+            learning_rate_schedule: Schedule::Constant,
+            validation: ValidationConfig::default()
+        };
+    }
+}
+
+impl Trainer {
+    /// Train the network using backpropagation, letting the optimizer step the weights and biases once per batch.
+    /// The gradients of a batch are averaged over its samples, so a smaller last batch takes a step of the same scale.
+    /// The optimizer keeps its state after training, so it can be used to continue the training later.
+    /// Its learning rate is set by the schedule at the start of every epoch and reset to the starting value at the end.
+    /// The score curve holds the negated mean loss of each epoch, measured on every batch before its step.
+    /// If there is a validation set, or the config splits one off the training set, the negated validation loss is measured after every epoch.
+    /// Both sets hold raw values, which are preprocessed by the pipelines of the network before training.
+    pub fn train_with_optimizer(&mut self, network : &NeuralNetwork, training_set : &[TrainingData], validation_set : &[TrainingData], config : &GradientConfig, optimizer : &mut dyn Optimizer) -> Result<TrainingResult, NetworkError> {
+        let (training_set, validation_set) = Trainer::validation_sets(training_set, validation_set, &config.validation);
+        let training_set = network.preprocess_training_set(&training_set)?;
+        let validation_set = network.preprocess_training_set(&validation_set)?;
+        Trainer::check_training_set(&training_set, network)?;
+        Trainer::check_training_set(&validation_set, network)?;
+        config.loss.check(network.nodes[network.nodes.len()-1].len())?;
+        let mut output_network = network.clone();
+        let mut validation = ValidationTracker::new(&config.validation);
+
+        let mut rng = StdRng::from_seed(&[config.seed as usize]);
+        let mut order : Vec<usize> = (0..training_set.len()).collect();
+        let batch_size = config.batch_size.samples(training_set.len());
+        let mut workspace = BatchWorkspace::new();
+        let validation_inputs = Trainer::input_matrix(&validation_set);
+
+        let base_learning_rate = optimizer.learning_rate();
+        let mut epoch_scores = vec![];
+        let mut learning_rate_curve = vec![];
+
+        self.notify_training_start(network, config.epochs);
+        for e in 0..config.epochs {
+            let learning_rate = config.learning_rate_schedule.value(base_learning_rate, e, &epoch_scores);
+            optimizer.set_learning_rate(learning_rate);
+            learning_rate_curve.push(learning_rate);
+
+            if config.shuffle {
+                rng.shuffle(&mut order);
+            }
+
+            // A stop requested after a batch ends the epoch early, which is then finished like a full one
+            let mut stop = false;
+            let mut epoch_loss = 0.0;
+            let mut samples = 0;
+            for (b, batch_indices) in order.chunks(batch_size).enumerate() {
+                if config.drop_last && batch_indices.len() < batch_size {
+                    break;
+                }
+                let batch : Vec<&TrainingData> = batch_indices.iter().map(|i| &training_set[*i]).collect();
+                let (gradients, loss) = self.calculate_batch_gradients(&output_network, &batch, &config.loss, &mut workspace)?;
+                optimizer.step(&mut output_network, &gradients);
+
+                epoch_loss += loss * batch.len() as f64;
+                samples += batch.len();
+
+                if self.notify_batch_end(e, b, loss) {
+                    stop = true;
+                    break;
+                }
+            }
+
+            let score = if samples > 0 { -epoch_loss / samples as f64 } else { 0.0 };
+            epoch_scores.push(score);
+
+            let mut validation_score = None;
+            if !validation_set.is_empty() {
+                let score = Trainer::evaluate_inputs_with_loss(&validation_set, &validation_inputs, &output_network, &config.loss, &mut workspace)?;
+                stop |= validation.record(score, &output_network);
+                validation_score = Some(score);
+            }
+
+            let info = GenerationInfo {
+                generation: e,
+                generations: config.epochs,
+                score,
+                validation_score,
+                rate: learning_rate,
+                network: &output_network
+            };
+            if self.notify_generation_end(&info) || stop {
+                break;
+            }
+        }
+        optimizer.set_learning_rate(base_learning_rate);
+
+        let result = TrainingResult {
+            network: validation.best_network.unwrap_or(output_network),
+            generation_score_curve: epoch_scores,
+            rate_curve: learning_rate_curve,
+            validation_score_curve: validation.scores,
+            best_generation: validation.best_generation
+        };
+        self.notify_training_end(&result);
+        return Ok(result);
+    }
+
+    /// Calculate the gradients of the loss averaged over a batch, and the mean loss of the batch.
+    /// The batch runs through the network at once, keeping the values of every layer in the workspace.
+    pub fn calculate_batch_gradients(&mut self, network : &NeuralNetwork, batch : &[&TrainingData], loss : &Loss, workspace : &mut BatchWorkspace) -> Result<(Gradients, f64), NetworkError> {
+        let mut gradients = Gradients::zeros(network);
+        if batch.is_empty() {
+            return Ok((gradients, 0.0));
+        }
+        for ts in batch {
+            network.check_input(&ts.input)?;
+            network.check_output(&ts.output)?;
+        }
+        loss.check(network.nodes[network.nodes.len()-1].len())?;
+
+        let inputs = Matrix::from_rows(batch.iter().map(|ts| ts.input.clone()).collect());
+        network.forward_batch(&inputs, workspace)?;
+
+        // Errors of the output layer, one row per sample
+        let last_layer_index = network.nodes.len()-1;
+        let mut errors = Matrix::new(batch.len(), network.nodes[last_layer_index].len());
+        let mut total_loss = 0.0;
+        for r in 0..batch.len() {
+            let output = workspace.nodes[last_layer_index].row(r);
+            total_loss += loss.calculate(output, &batch[r].output);
+            let output_errors = loss.output_errors(&network.activations[last_layer_index-1], workspace.weighted_sums[last_layer_index].row(r), output, &batch[r].output);
+            errors.row_mut(r).copy_from_slice(&output_errors);
+        }
+
+        // Add the gradients of every layer and propagate the errors back to the layer before it
+        for layer in (1..last_layer_index+1).rev() {
+            for r in 0..batch.len() {
+                gradients.weights[layer-1].add_outer(1.0, workspace.nodes[layer-1].row(r), errors.row(r));
+                crate::network::math::axpy(1.0, errors.row(r), &mut gradients.biases[layer]);
+            }
+
+            if layer > 1 {
+                let mut previous_errors = Matrix::new(batch.len(), network.nodes[layer-1].len());
+                for r in 0..batch.len() {
+                    let row = previous_errors.row_mut(r);
+                    row.copy_from_slice(&network.weights[layer-1].multiply_vector(errors.row(r)));
+                    network.activations[layer-2].backpropagate_layer(workspace.weighted_sums[layer-1].row(r), workspace.nodes[layer-1].row(r), row);
+                }
+                errors = previous_errors;
+            }
+        }
+
+        let count = batch.len() as f64;
+        gradients.scale(1.0 / count);
+        return Ok((gradients, total_loss / count));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{Activation, Initializer};
+    use crate::trainer::Adam;
+
+    #[test]
+    fn same_seed_gives_the_same_result() {
+        let training_set : Vec<TrainingData> = (0..20).map(|i| {
+            let x = i as f64 / 10.0;
+            return TrainingData::new(vec![x, 1.0 - x], vec![(3.0 * x).sin()]);
+        }).collect();
+        let network = NeuralNetwork::with_initializer(vec![2, 5, 1], vec![Activation::Tanh, Activation::Linear], Initializer::HeUniform, 11).unwrap();
+        let config = GradientConfig {
+            epochs: 10,
+            batch_size: BatchSize::Mini(3),
+            seed: 9,
+            validation: ValidationConfig { split: 0.2, seed: 4, ..ValidationConfig::default() },
+            ..GradientConfig::default()
+        };
+
+        let mut results = vec![];
+        for _ in 0..2 {
+            results.push(Trainer::new().train_with_optimizer(&network, &training_set, &[], &config, &mut Adam::new(0.01)).unwrap());
+        }
+        assert_eq!(results[0].generation_score_curve.len(), 10);
+        assert_eq!(results[0].validation_score_curve.len(), 10);
+        assert_eq!(results[0].generation_score_curve, results[1].generation_score_curve);
+        assert_eq!(results[0].validation_score_curve, results[1].validation_score_curve);
+        assert_eq!(results[0].network.weights, results[1].network.weights);
+        assert_eq!(results[0].network.biases, results[1].network.biases);
+    }
+
+    #[test]
+    fn batch_inputs_of_the_wrong_size_are_an_error() {
+        let network = NeuralNetwork::new(vec![2, 1]).unwrap();
+        let good = TrainingData::new(vec![0.0, 1.0], vec![1.0]);
+        let short = TrainingData::new(vec![1.0], vec![0.0]);
+        let result = Trainer::new().calculate_batch_gradients(&network, &[&good, &short], &Loss::MeanSquaredError, &mut BatchWorkspace::new());
+        assert_eq!(result.err(), Some(NetworkError::InputSizeMismatch { expected: 2, actual: 1 }));
+    }
+}
@@ -0,0 +1,220 @@
+use crate::network::{Activation, NetworkError};
+
+/// Smallest probability used in logarithms, so outputs of exactly zero do not give infinite losses
+const EPSILON : f64 = 1e-12;
+
+/// Measures how far the outputs of a network are from the expected outputs. Lower is better.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Loss {
+    /// Mean of the squared errors
+    MeanSquaredError,
+    /// Mean of the absolute errors
+    MeanAbsoluteError,
+    /// Squared error below the given delta and absolute error above it, mean over all outputs
+    Huber(f64),
+    /// Mean cross-entropy of independent probabilities, for sigmoid outputs and targets between 0 and 1
+    BinaryCrossEntropy,
+    /// Cross-entropy of one probability distribution over all outputs, for softmax outputs and one-hot targets
+    CategoricalCrossEntropy,
+    /// Mean of max(0, 1 - t * y). Targets above zero count as 1, all others as -1.
+    Hinge,
+    /// Another loss with a weight for every output
+    Weighted(Box<Loss>, Vec<f64>)
+}
+
+impl Default for Loss {
+    fn default() -> Loss {
+        return Loss::MeanSquaredError;
+    }
+}
+
+impl Loss {
+    /// Check that the output weights fit the number of outputs
+    pub fn check(&self, outputs : usize) -> Result<(), NetworkError> {
+        if let (_, Some(weights)) = self.unweighted() {
+            if weights.len() != outputs {
+                return Err(NetworkError::OutputSizeMismatch { expected: outputs, actual: weights.len() });
+            }
+            if weights.iter().any(|w| !w.is_finite()) {
+                return Err(NetworkError::NonFiniteValue("loss weight"));
+            }
+        }
+        return Ok(());
+    }
+
+    /// Loss of one output vector
+    pub fn calculate(&self, output : &[f64], expected : &[f64]) -> f64 {
+        let (loss, weights) = self.unweighted();
+        let mut total = 0.0;
+        for i in 0..output.len() {
+            total += weights.map_or(1.0, |w| w[i]) * loss.term(output[i], expected[i]);
+        }
+        return total / loss.normalization(output.len());
+    }
+
+    /// Derivative of the loss with respect to every output value
+    pub fn gradient(&self, output : &[f64], expected : &[f64]) -> Vec<f64> {
+        let (loss, weights) = self.unweighted();
+        let normalization = loss.normalization(output.len());
+        return (0..output.len()).map(|i| weights.map_or(1.0, |w| w[i]) * loss.term_derivative(output[i], expected[i]) / normalization).collect();
+    }
+
+    /// Derivative of the loss with respect to the weighted sums of the output layer.
+    /// Cross-entropy after a matching softmax or sigmoid layer is calculated directly, which stays exact when the outputs saturate.
+    pub fn output_errors(&self, activation : &Activation, sums : &[f64], output : &[f64], expected : &[f64]) -> Vec<f64> {
+        let (loss, weights) = self.unweighted();
+        let weight = |i : usize| weights.map_or(1.0, |w| w[i]);
+
+        match (loss, activation) {
+            (Loss::CategoricalCrossEntropy, Activation::Softmax) => {
+                let total_target : f64 = (0..output.len()).map(|i| weight(i) * expected[i]).sum();
+                return (0..output.len()).map(|i| output[i] * total_target - weight(i) * expected[i]).collect();
+            },
+            (Loss::BinaryCrossEntropy, Activation::Sigmoid) => {
+                let normalization = loss.normalization(output.len());
+                return (0..output.len()).map(|i| weight(i) * (output[i] - expected[i]) / normalization).collect();
+            },
+            _ => {
+                let mut errors = self.gradient(output, expected);
+                activation.backpropagate_layer(sums, output, &mut errors);
+                return errors;
+            }
+        }
+    }
+
+    /// The loss without output weights, and the weights if there are any
+    fn unweighted(&self) -> (&Loss, Option<&[f64]>) {
+        match self {
+            Loss::Weighted(inner, weights) => {
+                let (loss, _) = inner.unweighted();
+                return (loss, Some(weights));
+            },
+            _ => return (self, None)
+        }
+    }
+
+    /// Loss of a single output value
+    fn term(&self, y : f64, t : f64) -> f64 {
+        match *self {
+            Loss::MeanSquaredError => (y - t) * (y - t),
+            Loss::MeanAbsoluteError => (y - t).abs(),
+            Loss::Huber(delta) => {
+                let error = (y - t).abs();
+                if error <= delta { 0.5 * error * error } else { delta * (error - 0.5 * delta) }
+            },
+            Loss::BinaryCrossEntropy => {
+                let p = y.clamp(EPSILON, 1.0 - EPSILON);
+                -(t * p.ln() + (1.0 - t) * (1.0 - p).ln())
+            },
+            Loss::CategoricalCrossEntropy => -t * y.max(EPSILON).ln(),
+            Loss::Hinge => (1.0 - Loss::hinge_target(t) * y).max(0.0),
+            Loss::Weighted(ref inner, _) => inner.term(y, t)
+        }
+    }
+
+    /// Derivative of the loss of a single output value
+    fn term_derivative(&self, y : f64, t : f64) -> f64 {
+        match *self {
+            Loss::MeanSquaredError => 2.0 * (y - t),
+            Loss::MeanAbsoluteError => if y > t { 1.0 } else if y < t { -1.0 } else { 0.0 },
+            Loss::Huber(delta) => (y - t).clamp(-delta, delta),
+            Loss::BinaryCrossEntropy => {
+                let p = y.clamp(EPSILON, 1.0 - EPSILON);
+                (p - t) / (p * (1.0 - p))
+            },
+            Loss::CategoricalCrossEntropy => -t / y.max(EPSILON),
+            Loss::Hinge => {
+                let target = Loss::hinge_target(t);
+                if target * y < 1.0 { -target } else { 0.0 }
+            },
+            Loss::Weighted(ref inner, _) => inner.term_derivative(y, t)
+        }
+    }
+
+    /// Number the sum of all output terms is divided by
+    fn normalization(&self, outputs : usize) -> f64 {
+        match self {
+            // One distribution over all outputs, so the terms are summed up
+            Loss::CategoricalCrossEntropy => 1.0,
+            _ => outputs.max(1) as f64
+        }
+    }
+
+    fn hinge_target(t : f64) -> f64 {
+        return if t > 0.0 { 1.0 } else { -1.0 };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn activated(activation : &Activation, sums : &[f64]) -> Vec<f64> {
+        let mut values = vec![0.0; sums.len()];
+        activation.activate_layer(sums, &mut values);
+        return values;
+    }
+
+    /// Compare output_errors with central differences of the loss after the activation
+    fn check_output_errors(loss : &Loss, activation : &Activation, sums : &[f64], expected : &[f64]) {
+        let h = 1e-6;
+        let output = activated(activation, sums);
+        let errors = loss.output_errors(activation, sums, &output, expected);
+        for i in 0..sums.len() {
+            let mut plus = sums.to_vec();
+            let mut minus = sums.to_vec();
+            plus[i] += h;
+            minus[i] -= h;
+            let numeric = (loss.calculate(&activated(activation, &plus), expected) - loss.calculate(&activated(activation, &minus), expected)) / (2.0 * h);
+            assert!((numeric - errors[i]).abs() < 1e-6 * (1.0 + numeric.abs()), "{:?} with {:?}, output {}: {} != {}", loss, activation, i, errors[i], numeric);
+        }
+    }
+
+    #[test]
+    fn output_errors_match_finite_differences() {
+        let sums = [0.3, -0.7, 1.1];
+        let expected = [0.0, 1.0, 0.25];
+        let weights = vec![0.5, 2.0, 1.5];
+
+        let any_activation = [Activation::Sigmoid, Activation::Tanh, Activation::Relu, Activation::LeakyRelu(0.1), Activation::Elu(1.0), Activation::Gelu, Activation::Softplus, Activation::Linear, Activation::Softmax];
+        let any_output = [Loss::MeanSquaredError, Loss::MeanAbsoluteError, Loss::Huber(0.5), Loss::Hinge];
+        for loss in any_output {
+            for activation in &any_activation {
+                check_output_errors(&loss, activation, &sums, &expected);
+                check_output_errors(&Loss::Weighted(Box::new(loss.clone()), weights.clone()), activation, &sums, &expected);
+            }
+        }
+
+        // Cross-entropy needs probabilities, this includes the shortcuts for softmax and sigmoid layers
+        let one_hot = [0.0, 1.0, 0.0];
+        for loss in [Loss::BinaryCrossEntropy, Loss::CategoricalCrossEntropy] {
+            for activation in [Activation::Sigmoid, Activation::Softmax] {
+                for targets in [&expected, &one_hot] {
+                    check_output_errors(&loss, &activation, &sums, targets);
+                    check_output_errors(&Loss::Weighted(Box::new(loss.clone()), weights.clone()), &activation, &sums, targets);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn known_values() {
+        assert_eq!(Loss::MeanSquaredError.calculate(&[1.0, 3.0], &[0.0, 1.0]), 2.5);
+        assert_eq!(Loss::MeanAbsoluteError.calculate(&[1.0, 3.0], &[0.0, 1.0]), 1.5);
+        // Quadratic below delta, linear above it
+        assert_eq!(Loss::Huber(1.0).calculate(&[0.5, 3.0], &[0.0, 0.0]), (0.125 + 2.5) / 2.0);
+        assert_eq!(Loss::Hinge.calculate(&[0.5, -2.0], &[1.0, 0.0]), 0.25);
+        assert!((Loss::CategoricalCrossEntropy.calculate(&[0.25, 0.75], &[0.0, 1.0]) + 0.75f64.ln()).abs() < 1e-12);
+        assert_eq!(Loss::Weighted(Box::new(Loss::MeanSquaredError), vec![2.0, 0.0]).calculate(&[1.0, 3.0], &[0.0, 1.0]), 1.0);
+        assert!(Loss::BinaryCrossEntropy.calculate(&[0.0], &[1.0]).is_finite());
+    }
+
+    #[test]
+    fn check_rejects_weights_that_do_not_fit() {
+        let loss = Loss::Weighted(Box::new(Loss::MeanSquaredError), vec![1.0, 2.0]);
+        assert_eq!(loss.check(2), Ok(()));
+        assert_eq!(loss.check(3), Err(NetworkError::OutputSizeMismatch { expected: 3, actual: 2 }));
+        assert_eq!(Loss::Weighted(Box::new(Loss::Hinge), vec![f64::NAN]).check(1), Err(NetworkError::NonFiniteValue("loss weight")));
+        assert_eq!(Loss::Huber(1.0).check(5), Ok(()));
+    }
+}
@@ -0,0 +1,359 @@
+use serde::{Deserialize, Serialize};
+
+use crate::network::{Matrix, NeuralNetwork};
+
+/// Values for every weight and bias of a network, in the same layout as the network.
+/// Used for the gradients of the loss and for the per-parameter state of optimizers.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Gradients {
+    pub weights : Vec<Matrix>,
+    pub biases : Vec<Vec<f64>>
+}
+
+impl Gradients {
+    /// Zeros in the layout of the given network
+    pub fn zeros(network : &NeuralNetwork) -> Gradients {
+        return Gradients {
+            weights: network.weights.iter().map(|w| Matrix::new(w.rows(), w.columns())).collect(),
+            biases: network.biases.iter().map(|b| vec![0.0; b.len()]).collect()
+        };
+    }
+
+    /// Multiply every value by a factor
+    pub fn scale(&mut self, factor : f64) {
+        for slice in self.slices_mut() {
+            for value in slice.iter_mut() {
+                *value *= factor;
+            }
+        }
+    }
+
+    /// Add scale * other to these values
+    pub fn add_scaled(&mut self, scale : f64, other : &Gradients) {
+        for (slice, other_slice) in self.slices_mut().into_iter().zip(other.slices()) {
+            crate::network::math::axpy(scale, other_slice, slice);
+        }
+    }
+
+    /// Whether the layout matches the weights and biases of the network
+    pub fn matches(&self, network : &NeuralNetwork) -> bool {
+        return self.weights.len() == network.weights.len()
+            && self.weights.iter().zip(&network.weights).all(|(a, b)| a.rows() == b.rows() && a.columns() == b.columns() && a.data().len() == b.data().len())
+            && self.biases.len() == network.biases.len()
+            && self.biases.iter().zip(&network.biases).all(|(a, b)| a.len() == b.len());
+    }
+
+    /// All weight matrices followed by all bias vectors
+    pub fn slices(&self) -> Vec<&[f64]> {
+        let mut slices : Vec<&[f64]> = self.weights.iter().map(|w| w.data()).collect();
+        slices.extend(self.biases.iter().map(|b| b.as_slice()));
+        return slices;
+    }
+
+    /// All weight matrices followed by all bias vectors
+    pub fn slices_mut(&mut self) -> Vec<&mut [f64]> {
+        let mut slices : Vec<&mut [f64]> = self.weights.iter_mut().map(|w| w.data_mut()).collect();
+        slices.extend(self.biases.iter_mut().map(|b| b.as_mut_slice()));
+        return slices;
+    }
+}
+
+/// Weights and biases of a network in the same order as Gradients::slices
+fn network_slices(network : &mut NeuralNetwork) -> Vec<&mut [f64]> {
+    let mut slices : Vec<&mut [f64]> = network.weights.iter_mut().map(|w| w.data_mut()).collect();
+    slices.extend(network.biases.iter_mut().map(|b| b.as_mut_slice()));
+    return slices;
+}
+
+/// Return the state buffer, creating it in the layout of the network if it does not exist or does not fit anymore
+fn state_for<'a>(state : &'a mut Option<Gradients>, network : &NeuralNetwork) -> &'a mut Gradients {
+    if !state.as_ref().is_some_and(|s| s.matches(network)) {
+        *state = Some(Gradients::zeros(network));
+    }
+    return state.as_mut().unwrap();
+}
+
+/// Updates the weights and biases of a network from the gradients of the loss.
+/// Optimizers keep their state between steps. All optimizers in this module can be serialized with serde,
+/// so a training run can be paused and resumed with the same state.
+pub trait Optimizer {
+    /// Change the weights and biases of the network one step against the gradients
+    fn step(&mut self, network : &mut NeuralNetwork, gradients : &Gradients);
+
+    fn learning_rate(&self) -> f64;
+
+    fn set_learning_rate(&mut self, learning_rate : f64);
+}
+
+/// Plain gradient descent
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Sgd {
+    pub learning_rate : f64
+}
+
+impl Sgd {
+    pub fn new(learning_rate : f64) -> Sgd {
+        return Sgd { learning_rate };
+    }
+}
+
+impl Optimizer for Sgd {
+    fn step(&mut self, network : &mut NeuralNetwork, gradients : &Gradients) {
+        for (parameters, gradient) in network_slices(network).into_iter().zip(gradients.slices()) {
+            crate::network::math::axpy(-self.learning_rate, gradient, parameters);
+        }
+    }
+
+    fn learning_rate(&self) -> f64 {
+        return self.learning_rate;
+    }
+
+    fn set_learning_rate(&mut self, learning_rate : f64) {
+        self.learning_rate = learning_rate;
+    }
+}
+
+/// Gradient descent that keeps a velocity of the previous steps
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Momentum {
+    pub learning_rate : f64,
+    /// Fraction of the velocity kept every step
+    pub momentum : f64,
+    pub velocity : Option<Gradients>
+}
+
+impl Momentum {
+    pub fn new(learning_rate : f64, momentum : f64) -> Momentum {
+        return Momentum { learning_rate, momentum, velocity: None };
+    }
+}
+
+impl Optimizer for Momentum {
+    fn step(&mut self, network : &mut NeuralNetwork, gradients : &Gradients) {
+        let velocity = state_for(&mut self.velocity, network);
+        for ((parameters, gradient), v) in network_slices(network).into_iter().zip(gradients.slices()).zip(velocity.slices_mut()) {
+            for i in 0..parameters.len() {
+                v[i] = self.momentum * v[i] + gradient[i];
+                parameters[i] -= self.learning_rate * v[i];
+            }
+        }
+    }
+
+    fn learning_rate(&self) -> f64 {
+        return self.learning_rate;
+    }
+
+    fn set_learning_rate(&mut self, learning_rate : f64) {
+        self.learning_rate = learning_rate;
+    }
+}
+
+/// Momentum that looks ahead along the velocity before applying the gradient
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Nesterov {
+    pub learning_rate : f64,
+    /// Fraction of the velocity kept every step
+    pub momentum : f64,
+    pub velocity : Option<Gradients>
+}
+
+impl Nesterov {
+    pub fn new(learning_rate : f64, momentum : f64) -> Nesterov {
+        return Nesterov { learning_rate, momentum, velocity: None };
+    }
+}
+
+impl Optimizer for Nesterov {
+    fn step(&mut self, network : &mut NeuralNetwork, gradients : &Gradients) {
+        let velocity = state_for(&mut self.velocity, network);
+        for ((parameters, gradient), v) in network_slices(network).into_iter().zip(gradients.slices()).zip(velocity.slices_mut()) {
+            for i in 0..parameters.len() {
+                v[i] = self.momentum * v[i] + gradient[i];
+                parameters[i] -= self.learning_rate * (gradient[i] + self.momentum * v[i]);
+            }
+        }
+    }
+
+    fn learning_rate(&self) -> f64 {
+        return self.learning_rate;
+    }
+
+    fn set_learning_rate(&mut self, learning_rate : f64) {
+        self.learning_rate = learning_rate;
+    }
+}
+
+/// Scales the step of every parameter by the sum of all its squared gradients so far
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Adagrad {
+    pub learning_rate : f64,
+    pub epsilon : f64,
+    pub accumulated : Option<Gradients>
+}
+
+impl Adagrad {
+    pub fn new(learning_rate : f64) -> Adagrad {
+        return Adagrad { learning_rate, epsilon: 1e-8, accumulated: None };
+    }
+}
+
+impl Optimizer for Adagrad {
+    fn step(&mut self, network : &mut NeuralNetwork, gradients : &Gradients) {
+        let accumulated = state_for(&mut self.accumulated, network);
+        for ((parameters, gradient), sum) in network_slices(network).into_iter().zip(gradients.slices()).zip(accumulated.slices_mut()) {
+            for i in 0..parameters.len() {
+                sum[i] += gradient[i] * gradient[i];
+                parameters[i] -= self.learning_rate * gradient[i] / (sum[i].sqrt() + self.epsilon);
+            }
+        }
+    }
+
+    fn learning_rate(&self) -> f64 {
+        return self.learning_rate;
+    }
+
+    fn set_learning_rate(&mut self, learning_rate : f64) {
+        self.learning_rate = learning_rate;
+    }
+}
+
+/// Scales the step of every parameter by a moving average of its squared gradients
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RmsProp {
+    pub learning_rate : f64,
+    /// Fraction of the moving average kept every step
+    pub decay : f64,
+    pub epsilon : f64,
+    pub mean_square : Option<Gradients>
+}
+
+impl RmsProp {
+    pub fn new(learning_rate : f64) -> RmsProp {
+        return RmsProp { learning_rate, decay: 0.9, epsilon: 1e-8, mean_square: None };
+    }
+}
+
+impl Optimizer for RmsProp {
+    fn step(&mut self, network : &mut NeuralNetwork, gradients : &Gradients) {
+        let mean_square = state_for(&mut self.mean_square, network);
+        for ((parameters, gradient), average) in network_slices(network).into_iter().zip(gradients.slices()).zip(mean_square.slices_mut()) {
+            for i in 0..parameters.len() {
+                average[i] = self.decay * average[i] + (1.0 - self.decay) * gradient[i] * gradient[i];
+                parameters[i] -= self.learning_rate * gradient[i] / (average[i].sqrt() + self.epsilon);
+            }
+        }
+    }
+
+    fn learning_rate(&self) -> f64 {
+        return self.learning_rate;
+    }
+
+    fn set_learning_rate(&mut self, learning_rate : f64) {
+        self.learning_rate = learning_rate;
+    }
+}
+
+/// Adaptive moment estimation. With a weight decay above zero this is AdamW,
+/// which shrinks the weights directly instead of adding the decay to the gradients. Biases are not decayed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Adam {
+    pub learning_rate : f64,
+    pub beta1 : f64,
+    pub beta2 : f64,
+    pub epsilon : f64,
+    pub weight_decay : f64,
+    /// Number of steps taken, used to correct the bias of the moment estimates
+    pub steps : u64,
+    pub first_moment : Option<Gradients>,
+    pub second_moment : Option<Gradients>
+}
+
+impl Adam {
+    pub fn new(learning_rate : f64) -> Adam {
+        return Adam {
+            learning_rate,
+            beta1: 0.9,
+            beta2: 0.999,
+            epsilon: 1e-8,
+            weight_decay: 0.0,
+            steps: 0,
+            first_moment: None,
+            second_moment: None
+        };
+    }
+
+    /// AdamW with decoupled weight decay
+    pub fn with_weight_decay(learning_rate : f64, weight_decay : f64) -> Adam {
+        let mut adam = Adam::new(learning_rate);
+        adam.weight_decay = weight_decay;
+        return adam;
+    }
+}
+
+impl Optimizer for Adam {
+    fn step(&mut self, network : &mut NeuralNetwork, gradients : &Gradients) {
+        if !self.first_moment.as_ref().is_some_and(|m| m.matches(network)) {
+            self.steps = 0;
+        }
+        self.steps += 1;
+        let first_correction = 1.0 - self.beta1.powi(self.steps as i32);
+        let second_correction = 1.0 - self.beta2.powi(self.steps as i32);
+
+        let weight_layers = network.weights.len();
+        let first_moment = state_for(&mut self.first_moment, network);
+        let second_moment = state_for(&mut self.second_moment, network);
+        let slices = network_slices(network).into_iter().zip(gradients.slices()).zip(first_moment.slices_mut().into_iter().zip(second_moment.slices_mut()));
+        for (index, ((parameters, gradient), (m, v))) in slices.enumerate() {
+            let decay = if index < weight_layers { self.weight_decay } else { 0.0 };
+            for i in 0..parameters.len() {
+                m[i] = self.beta1 * m[i] + (1.0 - self.beta1) * gradient[i];
+                v[i] = self.beta2 * v[i] + (1.0 - self.beta2) * gradient[i] * gradient[i];
+                let step = (m[i] / first_correction) / ((v[i] / second_correction).sqrt() + self.epsilon);
+                parameters[i] -= self.learning_rate * (step + decay * parameters[i]);
+            }
+        }
+    }
+
+    fn learning_rate(&self) -> f64 {
+        return self.learning_rate;
+    }
+
+    fn set_learning_rate(&mut self, learning_rate : f64) {
+        self.learning_rate = learning_rate;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{Activation, Initializer};
+
+    #[test]
+    fn saved_state_must_fit_its_size() {
+        let result = serde_json::from_str::<Momentum>(r#"{"learning_rate": 0.1, "momentum": 0.9, "velocity": {"weights": [{"rows": 2, "columns": 2, "data": []}], "biases": [[], [0.0, 0.0]]}}"#);
+        assert!(result.is_err());
+
+        let overflowing = format!(r#"{{"rows": {}, "columns": 2, "data": []}}"#, usize::MAX);
+        assert!(serde_json::from_str::<Matrix>(&overflowing).is_err());
+    }
+
+    #[test]
+    fn saved_state_resumes_training() {
+        let mut network = NeuralNetwork::with_initializer(vec![2, 2], vec![Activation::Sigmoid], Initializer::XavierUniform, 1).unwrap();
+        let mut gradients = Gradients::zeros(&network);
+        gradients.weights[0][(0, 1)] = 0.5;
+        gradients.biases[1][0] = -0.25;
+
+        let mut optimizer = Momentum::new(0.1, 0.9);
+        optimizer.step(&mut network, &gradients);
+        let mut resumed : Momentum = serde_json::from_str(&serde_json::to_string(&optimizer).unwrap()).unwrap();
+        assert_eq!(resumed, optimizer);
+        assert!(resumed.velocity.as_ref().unwrap().matches(&network));
+
+        let mut copy = network.clone();
+        optimizer.step(&mut network, &gradients);
+        resumed.step(&mut copy, &gradients);
+        assert_eq!(network.weights, copy.weights);
+        assert_eq!(network.biases, copy.biases);
+    }
+}
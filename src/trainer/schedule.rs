@@ -0,0 +1,141 @@
+use std::f64::consts::PI;
+
+/// Changes a rate over the generations or epochs of a training run, starting from a base value.
+/// Used for the learning rate of gradient training and the mutation ammount of the genetic trainers.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Schedule {
+    /// Always the base value
+    Constant,
+    /// Multiply by the factor every step_size steps
+    Step { step_size : usize, factor : f64 },
+    /// Multiply by the factor every step
+    Exponential(f64),
+    /// Cosine curve from the base value down to the minimum, restarting after every period.
+    /// Every period is period_mult times as long as the one before it.
+    CosineWarmRestarts { period : usize, period_mult : usize, min_value : f64 },
+    /// Rise linearly to the base value over the given number of steps, then continue with the other schedule
+    LinearWarmup { steps : usize, then : Box<Schedule> },
+    /// Rise from the base value to the maximum over the first warmup_fraction of the steps,
+    /// then fall to the minimum until the last step. Both parts follow a cosine curve.
+    OneCycle { steps : usize, max_value : f64, min_value : f64, warmup_fraction : f64 },
+    /// Multiply by the factor whenever the score has not improved by more than min_delta for more than patience steps,
+    /// but never go below the minimum
+    ReduceOnPlateau { factor : f64, patience : usize, min_delta : f64, min_value : f64 }
+}
+
+impl Default for Schedule {
+    fn default() -> Schedule {
+        return Schedule::Constant;
+    }
+}
+
+impl Schedule {
+    /// Value at the given step, starting at step 0. Scores holds the score of every step before this one, higher is better.
+    pub fn value(&self, base : f64, step : usize, scores : &[f64]) -> f64 {
+        match self {
+            Schedule::Constant => return base,
+            Schedule::Step { step_size, factor } => return base * factor.powi((step / (*step_size).max(1)) as i32),
+            Schedule::Exponential(factor) => return base * factor.powi(step as i32),
+            Schedule::CosineWarmRestarts { period, period_mult, min_value } => {
+                // Find the period the step falls into
+                let mut length = (*period).max(1);
+                let mut position = step;
+                while position >= length {
+                    position -= length;
+                    length *= (*period_mult).max(1);
+                }
+                return min_value + (base - min_value) * 0.5 * (1.0 + (PI * position as f64 / length as f64).cos());
+            },
+            Schedule::LinearWarmup { steps, then } => {
+                if step < *steps {
+                    return base * (step + 1) as f64 / *steps as f64;
+                }
+                return then.value(base, step - steps, &scores[(*steps).min(scores.len())..]);
+            },
+            Schedule::OneCycle { steps, max_value, min_value, warmup_fraction } => {
+                let peak = ((*steps as f64 * warmup_fraction.clamp(0.0, 1.0)) as usize).max(1);
+                if step < peak {
+                    return Schedule::cosine_between(base, *max_value, step as f64 / peak as f64);
+                }
+                if step >= *steps {
+                    return *min_value;
+                }
+                return Schedule::cosine_between(*max_value, *min_value, (step - peak) as f64 / (steps - peak).max(1) as f64);
+            },
+            Schedule::ReduceOnPlateau { factor, patience, min_delta, min_value } => {
+                let mut value = base;
+                let mut best = f64::NEG_INFINITY;
+                let mut waiting = 0;
+                for score in scores {
+                    if *score > best + min_delta {
+                        best = *score;
+                        waiting = 0;
+                    }
+                    else {
+                        waiting += 1;
+                        if waiting > *patience {
+                            value = (value * factor).max(*min_value);
+                            waiting = 0;
+                        }
+                    }
+                }
+                return value;
+            }
+        }
+    }
+
+    /// Cosine interpolation from start to end, progress going from 0 to 1
+    fn cosine_between(start : f64, end : f64, progress : f64) -> f64 {
+        return end + (start - end) * 0.5 * (1.0 + (PI * progress).cos());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(schedule : &Schedule, base : f64, steps : &[usize]) -> Vec<f64> {
+        return steps.iter().map(|s| schedule.value(base, *s, &[])).collect();
+    }
+
+    fn assert_close(actual : &[f64], expected : &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for i in 0..actual.len() {
+            assert!((actual[i] - expected[i]).abs() < 1e-12, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn step_and_exponential() {
+        assert_close(&values(&Schedule::Constant, 0.3, &[0, 100]), &[0.3, 0.3]);
+        assert_close(&values(&Schedule::Step { step_size: 2, factor: 0.5 }, 1.0, &[0, 1, 2, 5]), &[1.0, 1.0, 0.5, 0.25]);
+        assert_close(&values(&Schedule::Exponential(0.9), 2.0, &[0, 1, 3]), &[2.0, 1.8, 2.0 * 0.729]);
+    }
+
+    #[test]
+    fn cosine_with_warm_restarts() {
+        // Periods of 4, 8 and 16 steps starting at 0, 4 and 12
+        let schedule = Schedule::CosineWarmRestarts { period: 4, period_mult: 2, min_value: 0.0 };
+        assert_close(&values(&schedule, 1.0, &[0, 2, 4, 8, 12]), &[1.0, 0.5, 1.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn warmup_and_one_cycle() {
+        let warmup = Schedule::LinearWarmup { steps: 4, then: Box::new(Schedule::Exponential(0.5)) };
+        assert_close(&values(&warmup, 1.0, &[0, 3, 4, 5]), &[0.25, 1.0, 1.0, 0.5]);
+
+        // Rises to the maximum over the first 3 steps, then falls to the minimum at step 11
+        let one_cycle = Schedule::OneCycle { steps: 11, max_value: 1.0, min_value: 0.0, warmup_fraction: 0.3 };
+        assert_close(&values(&one_cycle, 0.1, &[0, 3, 7, 11, 20]), &[0.1, 1.0, 0.5, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn reduce_on_plateau() {
+        let schedule = Schedule::ReduceOnPlateau { factor: 0.5, patience: 1, min_delta: 0.0, min_value: 0.3 };
+        let scores = [1.0, 2.0, 2.0, 2.0, 2.0, 2.0];
+        // Two steps without improvement halve the value, but it stops at the minimum
+        assert_eq!(schedule.value(1.0, 3, &scores[..3]), 1.0);
+        assert_eq!(schedule.value(1.0, 4, &scores[..4]), 0.5);
+        assert_eq!(schedule.value(1.0, 6, &scores), 0.3);
+    }
+}
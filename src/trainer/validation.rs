@@ -0,0 +1,175 @@
+use std::borrow::Cow;
+
+use rand::{Rng, SeedableRng, StdRng};
+
+use crate::network::NeuralNetwork;
+
+use super::{Trainer, TrainingData};
+
+/// Settings for checking a training run against data it does not train on
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationConfig {
+    /// Fraction of the training set held out for validation when no validation set is given. 0 disables the split.
+    pub split : f64,
+    /// Seed of the random number generator used to pick the held out samples
+    pub seed : u64,
+    /// Stop after this many generations without an improvement of the validation score. None trains all generations.
+    pub patience : Option<usize>,
+    /// How much the validation score has to rise to count as an improvement
+    pub min_delta : f64,
+    /// Return the network with the best validation score instead of the one from the last generation
+    pub restore_best : bool
+}
+
+impl Default for ValidationConfig {
+    fn default() -> ValidationConfig {
+        return ValidationConfig {
+            split: 0.0,
+            seed: 0,
+            patience: None,
+            min_delta: 0.0,
+            restore_best: true
+        };
+    }
+}
+
+impl Trainer {
+    /// Randomly split a fraction of the training set off for validation. Returns the remaining training set and the validation set,
+    /// both in their original order. At least one sample is kept for training.
+    pub fn split_validation(training_set : &[TrainingData], fraction : f64, seed : u64) -> (Vec<TrainingData>, Vec<TrainingData>) {
+        let count = ((training_set.len() as f64 * fraction.clamp(0.0, 1.0)).round() as usize).min(training_set.len().saturating_sub(1));
+
+        let mut rng = StdRng::from_seed(&[seed as usize]);
+        let mut order : Vec<usize> = (0..training_set.len()).collect();
+        rng.shuffle(&mut order);
+        let mut held_out = vec![false; training_set.len()];
+        for i in 0..count {
+            held_out[order[i]] = true;
+        }
+
+        let mut training = vec![];
+        let mut validation = vec![];
+        for i in 0..training_set.len() {
+            if held_out[i] {
+                validation.push(training_set[i].clone());
+            }
+            else {
+                training.push(training_set[i].clone());
+            }
+        }
+        return (training, validation);
+    }
+
+    /// The training and validation sets a run uses: the given validation set, or one split off the training set if it is empty
+    pub(super) fn validation_sets<'a>(training_set : &'a [TrainingData], validation_set : &'a [TrainingData], config : &ValidationConfig) -> (Cow<'a, [TrainingData]>, Cow<'a, [TrainingData]>) {
+        if validation_set.is_empty() && config.split > 0.0 {
+            let (training, validation) = Trainer::split_validation(training_set, config.split, config.seed);
+            return (Cow::Owned(training), Cow::Owned(validation));
+        }
+        return (Cow::Borrowed(training_set), Cow::Borrowed(validation_set));
+    }
+}
+
+/// Keeps track of the validation scores of a run, the best network so far and when to stop
+pub(super) struct ValidationTracker<'a> {
+    config : &'a ValidationConfig,
+    pub scores : Vec<f64>,
+    pub best_network : Option<NeuralNetwork>,
+    pub best_generation : Option<usize>,
+    best_score : f64,
+    waiting : usize
+}
+
+impl<'a> ValidationTracker<'a> {
+    pub fn new(config : &'a ValidationConfig) -> ValidationTracker<'a> {
+        return ValidationTracker {
+            config,
+            scores: vec![],
+            best_network: None,
+            best_generation: None,
+            best_score: f64::NEG_INFINITY,
+            waiting: 0
+        };
+    }
+
+    /// Record the validation score of the network of this generation. Returns true if training should stop.
+    pub fn record(&mut self, score : f64, network : &NeuralNetwork) -> bool {
+        if self.best_generation.is_none() || score > self.best_score + self.config.min_delta {
+            self.best_score = score;
+            self.best_generation = Some(self.scores.len());
+            if self.config.restore_best {
+                self.best_network = Some(network.clone());
+            }
+            self.waiting = 0;
+        }
+        else {
+            self.waiting += 1;
+        }
+        self.scores.push(score);
+
+        return self.config.patience.is_some_and(|patience| self.waiting >= patience);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{Activation, Matrix};
+    use crate::trainer::{GradientConfig, Loss, Sgd};
+
+    #[test]
+    fn split_keeps_the_order_and_one_training_sample() {
+        let set : Vec<TrainingData> = (0..10).map(|i| TrainingData::new(vec![i as f64], vec![0.0])).collect();
+        let (training, validation) = Trainer::split_validation(&set, 0.3, 5);
+        assert_eq!((training.len(), validation.len()), (7, 3));
+        let first = |set : &[TrainingData]| set.iter().map(|ts| ts.input()[0]).collect::<Vec<_>>();
+        assert!(first(&training).windows(2).all(|w| w[0] < w[1]) && first(&validation).windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(first(&Trainer::split_validation(&set, 0.3, 5).1), first(&validation));
+
+        assert_eq!(Trainer::split_validation(&set, 1.0, 0).0.len(), 1);
+    }
+
+    #[test]
+    fn tracker_stops_after_patience_and_keeps_the_best() {
+        let config = ValidationConfig { patience: Some(2), min_delta: 0.1, ..ValidationConfig::default() };
+        let mut tracker = ValidationTracker::new(&config);
+        let networks : Vec<NeuralNetwork> = (0..4).map(|i| NeuralNetwork::with_activations(vec![1, i + 1], vec![Activation::Linear]).unwrap()).collect();
+
+        // 2.05 is not better by more than min_delta
+        assert!(!tracker.record(1.0, &networks[0]));
+        assert!(!tracker.record(2.0, &networks[1]));
+        assert!(!tracker.record(2.05, &networks[2]));
+        assert!(tracker.record(1.5, &networks[3]));
+        assert_eq!(tracker.scores, vec![1.0, 2.0, 2.05, 1.5]);
+        assert_eq!(tracker.best_generation, Some(1));
+        assert_eq!(tracker.best_network.unwrap().get_structure(), vec![1, 2]);
+
+        let config = ValidationConfig { restore_best: false, ..ValidationConfig::default() };
+        let mut tracker = ValidationTracker::new(&config);
+        assert!(!tracker.record(1.0, &networks[0]));
+        assert!(tracker.best_network.is_none());
+    }
+
+    #[test]
+    fn training_stops_early_and_restores_the_best_network() {
+        // Training pulls the output towards 1, which only makes the validation loss with a target of 0 worse
+        let mut network = NeuralNetwork::with_activations(vec![1, 1], vec![Activation::Linear]).unwrap();
+        network.set_weights(vec![Matrix::from_vec(1, 1, vec![0.5])]).unwrap();
+        let training_set = vec![TrainingData::new(vec![1.0], vec![1.0])];
+        let validation_set = vec![TrainingData::new(vec![1.0], vec![0.0])];
+        let config = GradientConfig {
+            epochs: 100,
+            loss: Loss::MeanSquaredError,
+            validation: ValidationConfig { patience: Some(3), ..ValidationConfig::default() },
+            ..GradientConfig::default()
+        };
+
+        let result = Trainer::new().train_with_optimizer(&network, &training_set, &validation_set, &config, &mut Sgd::new(0.1)).unwrap();
+        assert_eq!(result.generation_score_curve.len(), 4);
+        assert_eq!(result.validation_score_curve.len(), 4);
+        assert_eq!(result.best_generation, Some(0));
+        // The network after the first epoch: one step of 0.1 * 2 * (0.5 - 1) on the weight and the bias
+        assert!((result.network.weights[0][(0, 0)] - 0.6).abs() < 1e-12);
+        assert!((result.network.biases[1][0] - 0.1).abs() < 1e-12);
+    }
+}